@@ -0,0 +1,54 @@
+//! Per-row vs vectorised assignment step.
+//!
+//! Run with `cargo bench --bench assignment` (add `--features blas` to use OpenBLAS).
+//!
+//! A plain timing loop rather than a benchmarking framework: running the koans compiles
+//! the dev-dependencies too, and they have to build offline on the workshop's toolchain.
+use koans::clustering::{closest_centroid, compute_cluster_memberships_vectorised};
+use ndarray::{Array, Array1, Array2, Axis};
+use ndarray_rand::rand::SeedableRng;
+use ndarray_rand::rand_distr::StandardNormal;
+use ndarray_rand::RandomExt;
+use rand_isaac::Isaac64Rng;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// How long to keep calling each implementation for.
+const MEASUREMENT_TIME: Duration = Duration::from_secs(2);
+
+/// The mean running time of `f`, after a warm-up call.
+fn time(mut f: impl FnMut() -> Array1<usize>) -> Duration {
+    black_box(f());
+    let start = Instant::now();
+    let mut n_runs = 0;
+    while start.elapsed() < MEASUREMENT_TIME {
+        black_box(f());
+        n_runs += 1;
+    }
+    start.elapsed() / n_runs
+}
+
+fn main() {
+    let mut rng = Isaac64Rng::seed_from_u64(42);
+    let n_observations = 10_000;
+    for &(n_features, n_centroids) in &[(2, 8), (16, 32), (64, 128)] {
+        let observations: Array2<f64> =
+            Array::random_using((n_observations, n_features), StandardNormal, &mut rng);
+        let centroids: Array2<f64> =
+            Array::random_using((n_centroids, n_features), StandardNormal, &mut rng);
+
+        let per_row = time(|| {
+            let centroids = black_box(&centroids);
+            black_box(&observations).map_axis(Axis(1), |observation| {
+                closest_centroid(centroids, &observation)
+            })
+        });
+        let vectorised = time(|| {
+            compute_cluster_memberships_vectorised(black_box(&centroids), black_box(&observations))
+        });
+        println!(
+            "assignment {}x{}, k={}: per_row {:?}, vectorised {:?}",
+            n_observations, n_features, n_centroids, per_row, vectorised
+        );
+    }
+}
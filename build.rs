@@ -1,20 +1,13 @@
-use std::fs::OpenOptions;
-use std::io::{ErrorKind, Write};
+use std::path::Path;
 
-fn main() {
-    let path = OpenOptions::new()
-        .create_new(true)
-        .write(true)
-        .open("src/path_to_enlightenment.rs");
+const PROGRESS_PATH: &str = "src/path_to_enlightenment.rs";
 
-    match path {
-        Err(error) => match error.kind() {
-            ErrorKind::AlreadyExists => {}
-            _ => panic!("{}", error),
-        },
-        Ok(f) => {
-            // Initialise as an empty file
-            write!(&f, "").unwrap();
-        }
+// The progress file is managed by the runner: the build only checks whether it exists,
+// so that `src/main.rs` can leave the koans out instead of creating an empty one.
+fn main() {
+    println!("cargo:rerun-if-changed={}", PROGRESS_PATH);
+    println!("cargo:rustc-check-cfg=cfg(missing_path_to_enlightenment)");
+    if !Path::new(PROGRESS_PATH).exists() {
+        println!("cargo:rustc-cfg=missing_path_to_enlightenment");
     }
 }
@@ -0,0 +1,174 @@
+use crate::session::Session;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// A learner's session, as exported with `koans report --export progress.json`
+/// and merged by facilitators with `koans aggregate <dir>`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Progress {
+    pub learner: String,
+    #[serde(flatten)]
+    pub session: Session,
+}
+
+impl Progress {
+    pub fn new(session: &Session) -> Self {
+        Self {
+            learner: learner_name(),
+            session: session.clone(),
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+/// The name used to identify a learner in exported progress files:
+/// `KOANS_LEARNER` if set, the current user otherwise.
+pub fn learner_name() -> String {
+    ["KOANS_LEARNER", "USER", "USERNAME"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|name| !name.is_empty())
+        .unwrap_or_else(|| "anonymous".into())
+}
+
+/// How a whole workshop room is doing on a single koan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KoanSummary {
+    pub id: String,
+    /// Learners whose first unsolved koan is this one.
+    pub n_stuck: usize,
+    pub n_solved: usize,
+    pub n_failures: u32,
+    /// Average seconds between opening and solving the koan, over the learners who solved it.
+    pub average_time_to_solve: Option<u64>,
+}
+
+/// Per-koan statistics merged from the progress files of many learners.
+#[derive(Debug, Clone)]
+pub struct Aggregate {
+    pub n_learners: usize,
+    pub n_completed: usize,
+    /// One entry for each koan opened by at least one learner, in path order.
+    pub koans: Vec<KoanSummary>,
+}
+
+impl Aggregate {
+    pub fn new(progresses: &[Progress]) -> Self {
+        let mut koans: BTreeMap<&str, (KoanSummary, Vec<u64>)> = BTreeMap::new();
+
+        for progress in progresses {
+            let session = &progress.session;
+            let stuck_on = session.current_koan().map(|k| k.id.as_str());
+            for record in &session.koans {
+                let (summary, times) = koans.entry(&record.id).or_insert_with(|| {
+                    let summary = KoanSummary {
+                        id: record.id.clone(),
+                        n_stuck: 0,
+                        n_solved: 0,
+                        n_failures: 0,
+                        average_time_to_solve: None,
+                    };
+                    (summary, Vec::new())
+                });
+                summary.n_failures += record.failures;
+                if let Some(time) = record.time_to_solve() {
+                    summary.n_solved += 1;
+                    times.push(time);
+                }
+                if stuck_on == Some(record.id.as_str()) {
+                    summary.n_stuck += 1;
+                }
+            }
+        }
+
+        Self {
+            n_learners: progresses.len(),
+            n_completed: progresses
+                .iter()
+                .filter(|p| p.session.is_completed())
+                .count(),
+            koans: koans
+                .into_iter()
+                .map(|(_, (mut summary, times))| {
+                    if !times.is_empty() {
+                        summary.average_time_to_solve =
+                            Some(times.iter().sum::<u64>() / times.len() as u64);
+                    }
+                    summary
+                })
+                .collect(),
+        }
+    }
+
+    /// The koan most learners are currently stuck on, if anybody is stuck at all.
+    pub fn bottleneck(&self) -> Option<&KoanSummary> {
+        self.koans
+            .iter()
+            .filter(|k| k.n_stuck > 0)
+            // `max_by_key` returns the last maximum: reverse to favour earlier koans on ties
+            .rev()
+            .max_by_key(|k| k.n_stuck)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(learner: &str, solved: &[&str], stuck_on: Option<&str>) -> Progress {
+        let mut session = Session::new();
+        for id in solved {
+            session.record_opened(id);
+            session.record_success(id);
+        }
+        if let Some(id) = stuck_on {
+            session.record_opened(id);
+            session.record_failure(id);
+        }
+        Progress {
+            learner: learner.into(),
+            session,
+        }
+    }
+
+    #[test]
+    fn stuck_learners_are_counted_on_their_first_unsolved_koan() {
+        let progresses = vec![
+            progress("alice", &["a", "b"], Some("c")),
+            progress("bob", &["a"], Some("b")),
+            progress("carol", &["a"], Some("b")),
+        ];
+        let aggregate = Aggregate::new(&progresses);
+
+        assert_eq!(aggregate.n_learners, 3);
+        let n_stuck: Vec<(&str, usize)> = aggregate
+            .koans
+            .iter()
+            .map(|k| (k.id.as_str(), k.n_stuck))
+            .collect();
+        assert_eq!(n_stuck, vec![("a", 0), ("b", 2), ("c", 1)]);
+        assert_eq!(aggregate.bottleneck().unwrap().id, "b");
+        assert_eq!(aggregate.koans[0].n_solved, 3);
+        assert_eq!(aggregate.koans[1].n_failures, 2);
+    }
+
+    #[test]
+    fn progress_files_round_trip() {
+        let progress = progress("alice", &["a"], Some("b"));
+        let json = serde_json::to_string(&progress).unwrap();
+        let parsed: Progress = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed.learner, "alice");
+        assert_eq!(parsed.session.koans.len(), 2);
+    }
+}
@@ -0,0 +1,317 @@
+//! Assignment steps that use the triangle inequality to skip distance computations.
+use super::distance::Distance;
+use super::Float;
+use ndarray::{Array1, Array2, ArrayBase, Data, Ix2};
+
+/// How the assignment step finds the closest centroid of each observation.
+///
+/// All variants produce the same cluster memberships (up to rounding, for observations
+/// almost equidistant from two centroids): they differ in how many distances they compute.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Algorithm {
+    /// Compute the distance between every observation and every centroid, at each iteration.
+    #[default]
+    Lloyd,
+    /// Keep an upper bound on the distance between each observation and its centroid,
+    /// as well as a lower bound on its distance from each other centroid: an observation is
+    /// only compared with the centroids its bounds cannot rule out.
+    ///
+    /// Skips the most distances, but needs `n_observations * n_clusters` bounds in memory.
+    Elkan,
+    /// Like [`Algorithm::Elkan`], with a single lower bound for each observation:
+    /// the distance from its second closest centroid.
+    ///
+    /// Lighter on memory, it works best when there are few clusters
+    /// (or few features, for the euclidean distance).
+    Hamerly,
+    /// Like [`Algorithm::Lloyd`], computing all the distances at once with a matrix product:
+    /// see [`compute_cluster_memberships_vectorised`](super::compute_cluster_memberships_vectorised).
+    /// Enable the `blas` feature to hand the product over to OpenBLAS.
+    ///
+    /// It only works for the euclidean distance (see [`Distance::is_euclidean`]):
+    /// other distances fall back to [`Algorithm::Lloyd`].
+    Vectorised,
+}
+
+/// The lower bounds kept by each accelerated algorithm.
+enum LowerBounds<A> {
+    /// `(n_observations, n_clusters)`: a lower bound on the distance from each centroid.
+    Elkan(Array2<A>),
+    /// `(n_observations,)`: a lower bound on the distance from the second closest centroid.
+    Hamerly(Array1<A>),
+}
+
+/// The state carried across the iterations of an accelerated algorithm.
+///
+/// Bounds only hold for metrics that satisfy the triangle inequality:
+/// see [`Distance::satisfies_triangle_inequality`].
+pub(crate) struct Bounds<A> {
+    /// `(n_observations,)`: the cluster each observation is currently assigned to.
+    pub(crate) memberships: Array1<usize>,
+    /// `(n_observations,)`: an upper bound on the distance from the assigned centroid.
+    upper: Array1<A>,
+    lower: LowerBounds<A>,
+    /// Has the assigned centroid moved since `upper` was last computed exactly?
+    stale: Vec<bool>,
+    /// The distances computed by Lloyd's algorithm minus the ones we actually computed,
+    /// centroid-to-centroid ones included.
+    pub(crate) n_skipped_distances: usize,
+}
+
+impl<A: Float> Bounds<A> {
+    /// Empty bounds: the first assignment step works them out.
+    ///
+    /// Returns `None` for [`Algorithm::Lloyd`] and [`Algorithm::Vectorised`],
+    /// which do not keep any bound.
+    pub(crate) fn new(
+        algorithm: Algorithm,
+        n_observations: usize,
+        n_clusters: usize,
+    ) -> Option<Self> {
+        let lower = match algorithm {
+            Algorithm::Lloyd | Algorithm::Vectorised => return None,
+            Algorithm::Elkan => LowerBounds::Elkan(Array2::zeros((n_observations, n_clusters))),
+            Algorithm::Hamerly => LowerBounds::Hamerly(Array1::zeros(n_observations)),
+        };
+        Some(Self {
+            memberships: Array1::zeros(n_observations),
+            upper: Array1::from_elem(n_observations, A::infinity()),
+            lower,
+            stale: vec![true; n_observations],
+            n_skipped_distances: 0,
+        })
+    }
+
+    /// Assign each observation to the closest centroid, updating the bounds on the way.
+    pub(crate) fn assign(
+        &mut self,
+        // (n_clusters, n_features)
+        centroids: &ArrayBase<impl Data<Elem = A>, Ix2>,
+        // (n_observations, n_features)
+        observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+        distance: &impl Distance,
+    ) {
+        let n_clusters = centroids.nrows();
+        // If d(c, c') >= 2 d(x, c), then d(x, c') >= d(x, c): c' cannot be closer than c.
+        let two = A::one() + A::one();
+        let mut half_distances = Array2::from_elem((n_clusters, n_clusters), A::infinity());
+        for i in 0..n_clusters {
+            for j in (i + 1)..n_clusters {
+                let half_distance = distance.distance(&centroids.row(i), &centroids.row(j)) / two;
+                half_distances[[i, j]] = half_distance;
+                half_distances[[j, i]] = half_distance;
+            }
+        }
+        // Half the distance from the closest other centroid
+        let half_separations: Vec<A> = half_distances
+            .genrows()
+            .into_iter()
+            .map(|row| row.iter().cloned().fold(A::infinity(), A::min))
+            .collect();
+
+        // The distances between each pair of centroids are not free either
+        let mut n_computed_distances = n_clusters * n_clusters.saturating_sub(1) / 2;
+        for (index, observation) in observations.genrows().into_iter().enumerate() {
+            let mut membership = self.memberships[index];
+            let mut upper = self.upper[index];
+            match &mut self.lower {
+                LowerBounds::Elkan(lower) => {
+                    if upper <= half_separations[membership] {
+                        continue;
+                    }
+                    let mut stale = self.stale[index];
+                    for cluster_index in 0..n_clusters {
+                        let ruled_out = |upper: A, membership: usize, lower: A| {
+                            upper <= lower || upper <= half_distances[[membership, cluster_index]]
+                        };
+                        if cluster_index == membership
+                            || ruled_out(upper, membership, lower[[index, cluster_index]])
+                        {
+                            continue;
+                        }
+                        if stale {
+                            upper = distance.distance(&observation, &centroids.row(membership));
+                            n_computed_distances += 1;
+                            lower[[index, membership]] = upper;
+                            stale = false;
+                            if ruled_out(upper, membership, lower[[index, cluster_index]]) {
+                                continue;
+                            }
+                        }
+                        let d = distance.distance(&observation, &centroids.row(cluster_index));
+                        n_computed_distances += 1;
+                        lower[[index, cluster_index]] = d;
+                        // Ties go to the lowest index, as in `closest_centroid_with`
+                        if d < upper || (d == upper && cluster_index < membership) {
+                            membership = cluster_index;
+                            upper = d;
+                        }
+                    }
+                    self.stale[index] = stale;
+                }
+                LowerBounds::Hamerly(lower) => {
+                    let bound = half_separations[membership].max(lower[index]);
+                    if upper <= bound {
+                        continue;
+                    }
+                    upper = distance.distance(&observation, &centroids.row(membership));
+                    n_computed_distances += 1;
+                    if upper > bound {
+                        let mut closest = (0, A::infinity());
+                        let mut second_closest = A::infinity();
+                        for (cluster_index, centroid) in centroids.genrows().into_iter().enumerate()
+                        {
+                            let d = if cluster_index == membership {
+                                upper
+                            } else {
+                                n_computed_distances += 1;
+                                distance.distance(&observation, &centroid)
+                            };
+                            if d < closest.1 {
+                                second_closest = closest.1;
+                                closest = (cluster_index, d);
+                            } else if d < second_closest {
+                                second_closest = d;
+                            }
+                        }
+                        membership = closest.0;
+                        upper = closest.1;
+                        lower[index] = second_closest;
+                    }
+                }
+            }
+            self.memberships[index] = membership;
+            self.upper[index] = upper;
+        }
+        self.n_skipped_distances +=
+            (observations.nrows() * n_clusters).saturating_sub(n_computed_distances);
+    }
+
+    /// Loosen the bounds to account for the centroids moving from `old_centroids`
+    /// to `new_centroids` during the update step.
+    pub(crate) fn shift(
+        &mut self,
+        // (n_clusters, n_features)
+        old_centroids: &ArrayBase<impl Data<Elem = A>, Ix2>,
+        // (n_clusters, n_features)
+        new_centroids: &ArrayBase<impl Data<Elem = A>, Ix2>,
+        distance: &impl Distance,
+    ) {
+        let shifts: Vec<A> = old_centroids
+            .genrows()
+            .into_iter()
+            .zip(new_centroids.genrows())
+            .map(|(old, new)| distance.distance(&old, &new))
+            .collect();
+        for (index, &membership) in self.memberships.iter().enumerate() {
+            self.upper[index] += shifts[membership];
+            if shifts[membership] > A::zero() {
+                self.stale[index] = true;
+            }
+        }
+        match &mut self.lower {
+            LowerBounds::Elkan(lower) => {
+                for mut row in lower.genrows_mut() {
+                    for (bound, &shift) in row.iter_mut().zip(&shifts) {
+                        *bound = (*bound - shift).max(A::zero());
+                    }
+                }
+            }
+            LowerBounds::Hamerly(lower) => {
+                // The largest shift, and the largest one among the other centroids
+                let mut largest = (0, A::zero());
+                let mut second_largest = A::zero();
+                for (cluster_index, &shift) in shifts.iter().enumerate() {
+                    if shift > largest.1 {
+                        second_largest = largest.1;
+                        largest = (cluster_index, shift);
+                    } else if shift > second_largest {
+                        second_largest = shift;
+                    }
+                }
+                for (bound, &membership) in lower.iter_mut().zip(&self.memberships) {
+                    *bound -= if membership == largest.0 {
+                        second_largest
+                    } else {
+                        largest.1
+                    };
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clustering::assignment::compute_cluster_memberships_with;
+    use crate::clustering::distance::{Euclidean, Manhattan};
+    use ndarray::Array;
+    use ndarray_rand::rand::SeedableRng;
+    use ndarray_rand::rand_distr::Uniform;
+    use ndarray_rand::RandomExt;
+    use rand_isaac::Isaac64Rng;
+
+    #[test]
+    fn bounds_survive_moving_centroids() {
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        let observations: Array2<f64> =
+            Array::random_using((300, 3), Uniform::new(-10., 10.), &mut rng);
+        let mut centroids: Array2<f64> =
+            Array::random_using((6, 3), Uniform::new(-10., 10.), &mut rng);
+
+        let mut elkan = Bounds::new(Algorithm::Elkan, 300, 6).unwrap();
+        let mut hamerly = Bounds::new(Algorithm::Hamerly, 300, 6).unwrap();
+        for step in 0..10 {
+            let expected = compute_cluster_memberships_with(&centroids, &observations, &Euclidean);
+            elkan.assign(&centroids, &observations, &Euclidean);
+            hamerly.assign(&centroids, &observations, &Euclidean);
+            assert_eq!(elkan.memberships, expected);
+            assert_eq!(hamerly.memberships, expected);
+            // Smaller and smaller moves, as when converging
+            let moves: Array2<f64> =
+                Array::random_using((6, 3), Uniform::new(-1., 1.), &mut rng) / (step + 1) as f64;
+            let new_centroids = &centroids + &moves;
+            elkan.shift(&centroids, &new_centroids, &Euclidean);
+            hamerly.shift(&centroids, &new_centroids, &Euclidean);
+            centroids = new_centroids;
+        }
+        assert!(elkan.n_skipped_distances > 0);
+        assert!(hamerly.n_skipped_distances > 0);
+
+        let mut elkan = Bounds::new(Algorithm::Elkan, 300, 6).unwrap();
+        for _ in 0..5 {
+            elkan.assign(&centroids, &observations, &Manhattan);
+            assert_eq!(
+                elkan.memberships,
+                compute_cluster_memberships_with(&centroids, &observations, &Manhattan)
+            );
+            let new_centroids =
+                &centroids + &Array::random_using((6, 3), Uniform::new(-0.5, 0.5), &mut rng);
+            elkan.shift(&centroids, &new_centroids, &Manhattan);
+            centroids = new_centroids;
+        }
+    }
+
+    #[test]
+    fn centroid_distances_count_as_computed() {
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        let observations: Array2<f64> =
+            Array::random_using((2, 3), Uniform::new(-10., 10.), &mut rng);
+        let centroids: Array2<f64> = Array::random_using((6, 3), Uniform::new(-10., 10.), &mut rng);
+
+        // The first step computes every observation-to-centroid distance, plus 15 between
+        // centroids: more than Lloyd's algorithm, with nothing skipped.
+        for &algorithm in &[Algorithm::Elkan, Algorithm::Hamerly] {
+            let mut bounds = Bounds::new(algorithm, 2, 6).unwrap();
+            bounds.assign(&centroids, &observations, &Euclidean);
+            assert_eq!(bounds.n_skipped_distances, 0);
+        }
+    }
+
+    #[test]
+    fn lloyd_keeps_no_bounds() {
+        assert!(Bounds::<f64>::new(Algorithm::Lloyd, 10, 2).is_none());
+        assert!(Bounds::<f64>::new(Algorithm::Vectorised, 10, 2).is_none());
+    }
+}
@@ -0,0 +1,249 @@
+//! Match fitted centroids with a reference set, e.g. the centroids a dataset was generated from.
+//!
+//! K-means does not know anything about the order of the clusters: the i-th fitted centroid
+//! can end up close to any of the reference centroids. [`align_centroids`] reorders
+//! the fitted centroids so that they can be compared with the reference ones row by row.
+use super::distance::{Distance, Euclidean};
+use super::Float;
+use ndarray::{Array1, Array2, ArrayBase, Axis, Data, Ix2};
+
+/// Solve the linear sum assignment problem with the Hungarian algorithm (Kuhn, 1955),
+/// in `O(n_rows² * n_columns)`.
+///
+/// Each row is assigned to a different column, minimising the total cost of the assignment:
+/// the output holds, for each row, the index of its column.
+///
+/// # Panics
+///
+/// If there are more rows than columns or if any cost is not finite.
+pub fn linear_sum_assignment<A: Float>(
+    // (n_rows, n_columns)
+    cost: &ArrayBase<impl Data<Elem = A>, Ix2>,
+) -> Array1<usize> {
+    let (n_rows, n_columns) = cost.dim();
+    assert!(
+        n_rows <= n_columns,
+        "There cannot be more rows than columns: each row needs its own column"
+    );
+    assert!(
+        cost.iter().all(|c| c.is_finite()),
+        "All costs must be finite"
+    );
+
+    // Potentials for rows and columns, with an extra dummy column (index 0) that holds
+    // the row we are currently trying to assign. Rows and columns are 1-indexed from here on.
+    let mut row_potentials = vec![A::zero(); n_rows + 1];
+    let mut column_potentials = vec![A::zero(); n_columns + 1];
+    // The row assigned to each column, 0 if none
+    let mut column_assignment = vec![0; n_columns + 1];
+    // The previous column on the shortest augmenting path
+    let mut way = vec![0; n_columns + 1];
+
+    for row in 1..=n_rows {
+        column_assignment[0] = row;
+        let mut current_column = 0;
+        let mut min_slack = vec![A::infinity(); n_columns + 1];
+        let mut visited = vec![false; n_columns + 1];
+        // Grow a tree of tight edges until it reaches a free column
+        loop {
+            visited[current_column] = true;
+            let current_row = column_assignment[current_column];
+            let mut delta = A::infinity();
+            let mut next_column = 0;
+            for column in 1..=n_columns {
+                if visited[column] {
+                    continue;
+                }
+                let slack = cost[[current_row - 1, column - 1]]
+                    - row_potentials[current_row]
+                    - column_potentials[column];
+                if slack < min_slack[column] {
+                    min_slack[column] = slack;
+                    way[column] = current_column;
+                }
+                if min_slack[column] < delta {
+                    delta = min_slack[column];
+                    next_column = column;
+                }
+            }
+            for column in 0..=n_columns {
+                if visited[column] {
+                    row_potentials[column_assignment[column]] += delta;
+                    column_potentials[column] -= delta;
+                } else {
+                    min_slack[column] -= delta;
+                }
+            }
+            current_column = next_column;
+            if column_assignment[current_column] == 0 {
+                break;
+            }
+        }
+        // Flip the augmenting path
+        while current_column != 0 {
+            let previous_column = way[current_column];
+            column_assignment[current_column] = column_assignment[previous_column];
+            current_column = previous_column;
+        }
+    }
+
+    let mut row_assignment = Array1::zeros(n_rows);
+    for (column, &row) in column_assignment.iter().enumerate().skip(1) {
+        if row != 0 {
+            row_assignment[row - 1] = column - 1;
+        }
+    }
+    row_assignment
+}
+
+/// Match each reference centroid with a different fitted centroid, minimising the total
+/// distance between matched pairs: see [`linear_sum_assignment`].
+///
+/// The output holds, for each reference centroid, the index of the matching row in `centroids`.
+///
+/// # Panics
+///
+/// If there are fewer fitted centroids than reference centroids, or if they have
+/// a different number of features.
+pub fn match_centroids_with<A: Float>(
+    // (n_clusters, n_features)
+    centroids: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    // (n_reference_clusters, n_features)
+    reference_centroids: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    distance: &impl Distance,
+) -> Array1<usize> {
+    assert!(
+        reference_centroids.nrows() <= centroids.nrows(),
+        "There must be at least as many centroids as reference centroids"
+    );
+    let mut cost = Array2::zeros((reference_centroids.nrows(), centroids.nrows()));
+    for (mut row, reference) in cost
+        .genrows_mut()
+        .into_iter()
+        .zip(reference_centroids.genrows())
+    {
+        for (c, centroid) in row.iter_mut().zip(centroids.genrows()) {
+            *c = distance.distance(&reference, &centroid);
+        }
+    }
+    linear_sum_assignment(&cost)
+}
+
+/// Match each reference centroid with a different fitted centroid, using the euclidean distance:
+/// see [`match_centroids_with`].
+pub fn match_centroids<A: Float>(
+    // (n_clusters, n_features)
+    centroids: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    // (n_reference_clusters, n_features)
+    reference_centroids: &ArrayBase<impl Data<Elem = A>, Ix2>,
+) -> Array1<usize> {
+    match_centroids_with(centroids, reference_centroids, &Euclidean)
+}
+
+/// Reorder `centroids` so that the i-th row is the one matched with the i-th reference centroid,
+/// using the euclidean distance: see [`match_centroids_with`].
+///
+/// The output has shape `(n_reference_clusters, n_features)`: fitted centroids that do not match
+/// any reference centroid are left out.
+///
+/// ```
+/// use koans::clustering::alignment::align_centroids;
+/// use ndarray::array;
+///
+/// let reference_centroids = array![[0., 0.], [10., 10.], [-10., 10.]];
+/// let centroids = array![[-9.9, 10.1], [0.1, -0.2], [10.2, 9.9]];
+///
+/// let aligned = align_centroids(&centroids, &reference_centroids);
+/// assert_eq!(aligned, array![[0.1, -0.2], [10.2, 9.9], [-9.9, 10.1]]);
+/// ```
+pub fn align_centroids<A: Float>(
+    // (n_clusters, n_features)
+    centroids: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    // (n_reference_clusters, n_features)
+    reference_centroids: &ArrayBase<impl Data<Elem = A>, Ix2>,
+) -> Array2<A> {
+    let matches = match_centroids(centroids, reference_centroids);
+    centroids.select(Axis(0), matches.as_slice().expect("Freshly allocated"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clustering::distance::Manhattan;
+    use crate::clustering::initialisation::sample_indices;
+    use approx::assert_abs_diff_eq;
+    use ndarray::{array, stack, Array};
+    use ndarray_rand::rand::SeedableRng;
+    use ndarray_rand::rand_distr::Uniform;
+    use ndarray_rand::RandomExt;
+    use rand_isaac::Isaac64Rng;
+
+    /// The cheapest assignment, trying all of them.
+    fn brute_force(cost: &Array2<f64>) -> f64 {
+        fn search(cost: &Array2<f64>, row: usize, used: &mut Vec<bool>) -> f64 {
+            if row == cost.nrows() {
+                return 0.;
+            }
+            let mut best = f64::INFINITY;
+            for column in 0..cost.ncols() {
+                if !used[column] {
+                    used[column] = true;
+                    best = best.min(cost[[row, column]] + search(cost, row + 1, used));
+                    used[column] = false;
+                }
+            }
+            best
+        }
+        search(cost, 0, &mut vec![false; cost.ncols()])
+    }
+
+    #[test]
+    fn hungarian_finds_the_cheapest_assignment() {
+        // A greedy matcher would pick (0, 0) and pay 1 + 100
+        let cost = array![[1., 2.], [3., 100.]];
+        assert_eq!(linear_sum_assignment(&cost), array![1, 0]);
+
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        for &(n_rows, n_columns) in &[(1, 1), (4, 4), (5, 7), (6, 6), (3, 8)] {
+            let cost: Array2<f64> =
+                Array::random_using((n_rows, n_columns), Uniform::new(-5., 10.), &mut rng);
+            let assignment = linear_sum_assignment(&cost);
+
+            let mut columns = assignment.to_vec();
+            columns.sort();
+            columns.dedup();
+            assert_eq!(columns.len(), n_rows, "Each row gets its own column");
+            let total: f64 = assignment
+                .iter()
+                .enumerate()
+                .map(|(row, &column)| cost[[row, column]])
+                .sum();
+            assert_abs_diff_eq!(total, brute_force(&cost), epsilon = 1e-9);
+        }
+    }
+
+    #[test]
+    fn centroids_are_aligned_with_the_reference() {
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        let reference_centroids = array![[0., 0.], [10., 10.], [-10., 10.], [5., -20.]];
+        let permutation = sample_indices(4, 4, &mut rng);
+        let noise: Array2<f64> = Array::random_using((4, 2), Uniform::new(-1., 1.), &mut rng);
+        let centroids = reference_centroids.select(Axis(0), &permutation) + noise;
+
+        let matches = match_centroids(&centroids, &reference_centroids);
+        for (reference_index, &index) in matches.iter().enumerate() {
+            assert_eq!(permutation[index], reference_index);
+        }
+        assert_eq!(
+            match_centroids_with(&centroids, &reference_centroids, &Manhattan),
+            matches
+        );
+        let aligned = align_centroids(&centroids, &reference_centroids);
+        assert_abs_diff_eq!(aligned, reference_centroids, epsilon = 1.);
+
+        // Extra fitted centroids are left out
+        let extra = array![[100., 100.]];
+        let centroids = stack(Axis(0), &[extra.view(), centroids.view()]).unwrap();
+        assert_eq!(align_centroids(&centroids, &reference_centroids), aligned);
+    }
+}
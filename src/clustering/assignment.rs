@@ -0,0 +1,318 @@
+//! Assign each observation to the nearest cluster.
+use super::distance::{Distance, Euclidean};
+use super::Float;
+#[cfg(feature = "rayon")]
+use ndarray::s;
+use ndarray::{Array1, Array2, ArrayBase, ArrayView1, Axis, Data, Ix1, Ix2};
+
+/// The euclidean distance between two n-dimensional vectors:
+///
+/// d(a, b) = sqrt[ (a₁ - b₁)² + ... + (aₙ - bₙ)²]
+///
+/// # Panics
+///
+/// If `a` and `b` have different lengths.
+pub fn euclidean_distance<A: Float>(
+    a: &ArrayBase<impl Data<Elem = A>, Ix1>,
+    b: &ArrayBase<impl Data<Elem = A>, Ix1>,
+) -> A {
+    assert_eq!(
+        a.len(),
+        b.len(),
+        "The two vectors must have the same length"
+    );
+    a.iter()
+        .zip(b.iter())
+        .map(|(&a, &b)| (a - b).powi(2))
+        .sum::<A>()
+        .sqrt()
+}
+
+/// Given a matrix of centroids with shape `(n_centroids, n_features)` and an observation,
+/// return the index of the closest centroid (the index of the corresponding row in `centroids`).
+///
+/// Ties are broken in favour of the centroid with the lowest index.
+pub fn closest_centroid<A: Float>(
+    centroids: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    observation: &ArrayBase<impl Data<Elem = A>, Ix1>,
+) -> usize {
+    closest_centroid_with(centroids, observation, &Euclidean)
+}
+
+/// Like [`closest_centroid`], measuring closeness with `distance`.
+pub fn closest_centroid_with<A: Float>(
+    centroids: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    observation: &ArrayBase<impl Data<Elem = A>, Ix1>,
+    distance: &impl Distance,
+) -> usize {
+    let mut closest_index = 0;
+    let mut minimum_distance = A::infinity();
+    for (index, centroid) in centroids.genrows().into_iter().enumerate() {
+        let distance = distance.distance(&centroid, observation);
+        if distance < minimum_distance {
+            minimum_distance = distance;
+            closest_index = index;
+        }
+    }
+    closest_index
+}
+
+/// Given a matrix of centroids with shape `(n_centroids, n_features)`
+/// and a matrix of observations with shape `(n_observations, n_features)`,
+/// return a `(n_observations,)` array where the i-th element is the index of the centroid
+/// closest to the i-th observation.
+pub fn compute_cluster_memberships<A: Float>(
+    centroids: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+) -> Array1<usize> {
+    compute_cluster_memberships_with(centroids, observations, &Euclidean)
+}
+
+/// Like [`compute_cluster_memberships`], measuring closeness with `distance`.
+///
+/// With the `rayon` feature enabled, chunks of [`PARALLEL_CHUNK_SIZE`] observations
+/// are assigned in parallel. Each observation is assigned independently of the others:
+/// the result is the same as the sequential one.
+pub fn compute_cluster_memberships_with<A: Float>(
+    centroids: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    distance: &impl Distance,
+) -> Array1<usize> {
+    #[cfg(feature = "rayon")]
+    {
+        use ndarray::stack;
+        use rayon::prelude::*;
+
+        let (centroids, observations) = (centroids.view(), observations.view());
+        let chunks: Vec<Array1<usize>> = row_chunks(observations.nrows())
+            .into_par_iter()
+            .map(|rows| {
+                let chunk = observations.slice(s![rows, ..]);
+                chunk.map_axis(Axis(1), |observation| {
+                    closest_centroid_with(&centroids, &observation, distance)
+                })
+            })
+            .collect();
+        let chunks: Vec<_> = chunks.iter().map(|chunk| chunk.view()).collect();
+        match chunks.len() {
+            0 => Array1::zeros(0),
+            _ => stack(Axis(0), &chunks).expect("Chunks are one-dimensional"),
+        }
+    }
+    #[cfg(not(feature = "rayon"))]
+    observations.map_axis(Axis(1), |observation| {
+        closest_centroid_with(centroids, &observation, distance)
+    })
+}
+
+/// The number of observations processed by each parallel task
+/// when the `rayon` feature is enabled.
+///
+/// Chunks do not depend on the number of threads: neither do the results.
+pub const PARALLEL_CHUNK_SIZE: usize = 8192;
+
+/// Split `0..n_observations` into consecutive ranges of (at most) [`PARALLEL_CHUNK_SIZE`] rows.
+#[cfg(feature = "rayon")]
+pub(crate) fn row_chunks(n_observations: usize) -> Vec<std::ops::Range<usize>> {
+    (0..n_observations)
+        .step_by(PARALLEL_CHUNK_SIZE)
+        .map(|start| start..n_observations.min(start + PARALLEL_CHUNK_SIZE))
+        .collect()
+}
+
+/// The squared euclidean distance between each observation and each centroid,
+/// as a `(n_observations, n_centroids)` matrix.
+///
+/// Instead of looping over each pair, we expand the square:
+///
+/// ```text
+/// ‖x - c‖² = ‖x‖² - 2 x·c + ‖c‖²
+/// ```
+///
+/// The `x·c` term for all pairs is a single matrix product, `observations · centroidsᵀ`,
+/// which `ndarray` can hand over to an optimised BLAS implementation
+/// (enable the `blas` feature to use OpenBLAS).
+///
+/// The expansion is subject to cancellation: results can differ from the direct computation
+/// by a few ULPs of `‖x‖² + ‖c‖²` (negative values are clamped to zero).
+///
+/// # Panics
+///
+/// If `observations` and `centroids` have a different number of features.
+pub fn pairwise_squared_distances<A: Float>(
+    // (n_observations, n_features)
+    observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    // (n_centroids, n_features)
+    centroids: &ArrayBase<impl Data<Elem = A>, Ix2>,
+) -> Array2<A> {
+    assert_eq!(
+        observations.ncols(),
+        centroids.ncols(),
+        "Observations and centroids must have the same number of features"
+    );
+    let squared_norm = |row: ArrayView1<A>| row.dot(&row);
+    let observation_norms = observations.map_axis(Axis(1), squared_norm);
+    let centroid_norms = centroids.map_axis(Axis(1), squared_norm);
+
+    let two = A::one() + A::one();
+    let mut distances = observations.dot(&centroids.t()) * (-two);
+    distances += &observation_norms.insert_axis(Axis(1));
+    distances += &centroid_norms;
+    distances.mapv_inplace(|d| d.max(A::zero()));
+    distances
+}
+
+/// Same output as [`compute_cluster_memberships`], computed from the
+/// [`pairwise_squared_distances`] matrix instead of one observation at a time.
+///
+/// [`KMeans`](super::KMeans) only uses it if asked to, see
+/// [`Algorithm::Vectorised`](super::Algorithm::Vectorised): a matrix product (possibly BLAS-backed)
+/// does not always beat looping over each observation.
+///
+/// Observations (almost) equidistant from two centroids might be assigned differently,
+/// due to the rounding errors of the two approaches.
+pub fn compute_cluster_memberships_vectorised<A: Float>(
+    centroids: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+) -> Array1<usize> {
+    pairwise_squared_distances(observations, centroids).map_axis(Axis(1), |distances| {
+        // Ties are broken in favour of the centroid with the lowest index
+        let mut closest_index = 0;
+        for (index, &distance) in distances.iter().enumerate() {
+            if distance < distances[closest_index] {
+                closest_index = index;
+            }
+        }
+        closest_index
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clustering::distance::{Chebyshev, Manhattan};
+    use approx::assert_abs_diff_eq;
+    use ndarray::{array, Array};
+    use ndarray_rand::rand::SeedableRng;
+    use ndarray_rand::rand_distr::Uniform;
+    use ndarray_rand::RandomExt;
+    use rand_isaac::Isaac64Rng;
+
+    #[test]
+    fn euclidean_distance_oracle() {
+        let a = array![0., 1., 4., 2.];
+        let b = array![1., 1., 2., 4.];
+
+        assert_eq!(euclidean_distance(&a, &b), 3.);
+        assert_eq!(euclidean_distance(&b.view(), &a), 3.);
+        assert_eq!(
+            euclidean_distance(&a.mapv(|x| x as f32), &b.mapv(|x| x as f32)),
+            3.
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn euclidean_distance_with_different_lengths() {
+        euclidean_distance(&array![0., 1., 2.], &array![0., 1., 2., 3.]);
+    }
+
+    #[test]
+    fn memberships_oracle() {
+        let centroids = array![[0., 0.], [1., 2.], [20., 0.], [0., 20.],];
+        let observations = array![[1., 0.5], [20., 2.], [20., 0.], [7., 20.],];
+
+        assert_eq!(closest_centroid(&centroids, &array![20.5, 0.5]), 2);
+        assert_eq!(
+            compute_cluster_memberships(&centroids, &observations),
+            array![0, 2, 2, 3]
+        );
+        assert_eq!(
+            compute_cluster_memberships(&centroids, &centroids),
+            array![0, 1, 2, 3]
+        );
+        assert_eq!(
+            compute_cluster_memberships(
+                &centroids.mapv(|x| x as f32),
+                &observations.mapv(|x| x as f32)
+            ),
+            array![0, 2, 2, 3]
+        );
+    }
+
+    #[test]
+    fn memberships_depend_on_the_distance() {
+        let centroids = array![[2., 2.], [3., 0.], [2.2, 1.]];
+        let observation = array![0., 0.];
+
+        // Euclidean: [2.83, 3, 2.42]
+        assert_eq!(closest_centroid(&centroids, &observation), 2);
+        // Manhattan: [4, 3, 3.2]
+        assert_eq!(
+            closest_centroid_with(&centroids, &observation, &Manhattan),
+            1
+        );
+        // Chebyshev: [2, 3, 2.2]
+        assert_eq!(
+            compute_cluster_memberships_with(
+                &centroids,
+                &observation.insert_axis(Axis(0)),
+                &Chebyshev
+            ),
+            array![0]
+        );
+    }
+
+    #[test]
+    fn vectorised_memberships_match_the_per_row_ones() {
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        let observations: Array2<f64> =
+            Array::random_using((500, 8), Uniform::new(-10., 10.), &mut rng);
+        let centroids: Array2<f64> = Array::random_using((7, 8), Uniform::new(-10., 10.), &mut rng);
+
+        let distances = pairwise_squared_distances(&observations, &centroids);
+        assert_eq!(distances.dim(), (500, 7));
+        for ((i, j), &distance) in distances.indexed_iter() {
+            let expected = euclidean_distance(&observations.row(i), &centroids.row(j)).powi(2);
+            assert_abs_diff_eq!(distance, expected, epsilon = 1e-9);
+        }
+        let per_row = observations.map_axis(Axis(1), |observation| {
+            closest_centroid(&centroids, &observation)
+        });
+        assert_eq!(
+            compute_cluster_memberships_vectorised(&centroids, &observations),
+            per_row
+        );
+        assert_eq!(
+            compute_cluster_memberships_vectorised(
+                &centroids.mapv(|x| x as f32),
+                &observations.mapv(|x| x as f32)
+            ),
+            per_row
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_memberships_match_the_sequential_ones() {
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        let n_observations = 2 * PARALLEL_CHUNK_SIZE + 17;
+        let observations: Array2<f64> =
+            Array::random_using((n_observations, 3), Uniform::new(-10., 10.), &mut rng);
+        let centroids: Array2<f64> = Array::random_using((5, 3), Uniform::new(-10., 10.), &mut rng);
+
+        let sequential = observations.map_axis(Axis(1), |observation| {
+            closest_centroid(&centroids, &observation)
+        });
+        let parallel = compute_cluster_memberships(&centroids, &observations);
+        assert_eq!(parallel, sequential);
+        let sequential = observations.map_axis(Axis(1), |observation| {
+            closest_centroid_with(&centroids, &observation, &Manhattan)
+        });
+        let parallel = compute_cluster_memberships_with(&centroids, &observations, &Manhattan);
+        assert_eq!(parallel, sequential);
+
+        let empty = Array2::<f64>::zeros((0, 3));
+        assert_eq!(compute_cluster_memberships(&centroids, &empty).len(), 0);
+    }
+}
@@ -0,0 +1,299 @@
+//! How far apart are two observations?
+//!
+//! K-means is usually taught with the euclidean distance, but the same assign-then-update loop
+//! works with other metrics, as long as the update step is adapted to match:
+//! each [`Distance`] tells the update step which kind of [`Centre`] it should compute.
+use super::error::KMeansError;
+use super::Float;
+use ndarray::{ArrayBase, Data, Ix1};
+use std::fmt::Debug;
+
+/// The centre of a cluster, according to a [`Distance`]: the point that (approximately)
+/// minimises the sum of its costs from the observations in the cluster.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Centre {
+    /// The mean of each feature: the minimiser for the squared euclidean distance.
+    Mean,
+    /// The median of each feature: the minimiser for the Manhattan distance (k-medians).
+    Median,
+    /// The mean, rescaled to unit length: spherical k-means, for the cosine distance.
+    NormalisedMean,
+}
+
+/// A metric to assign observations to centroids.
+///
+/// Implementors are small `Copy` values, cheap to pass around (and to share between threads).
+pub trait Distance: Debug + Clone + Copy + Send + Sync {
+    /// The distance between `a` and `b`.
+    ///
+    /// # Panics
+    ///
+    /// If `a` and `b` have different lengths.
+    fn distance<A: Float>(
+        &self,
+        a: &ArrayBase<impl Data<Elem = A>, Ix1>,
+        b: &ArrayBase<impl Data<Elem = A>, Ix1>,
+    ) -> A;
+
+    /// The contribution of an observation at `distance` from its centroid to
+    /// the objective we are minimising (e.g. the inertia, for the euclidean distance).
+    fn cost<A: Float>(&self, distance: A) -> A {
+        distance
+    }
+
+    /// Which centre the update step should compute for each cluster.
+    fn centre(&self) -> Centre {
+        Centre::Mean
+    }
+
+    /// Does `d(a, c) <= d(a, b) + d(b, c)` hold for all `a`, `b` and `c`?
+    ///
+    /// Accelerated algorithms rely on it to skip distance computations.
+    fn satisfies_triangle_inequality(&self) -> bool {
+        true
+    }
+
+    /// Does it rank centroids like the euclidean distance (e.g. it is the euclidean distance
+    /// or its square)?
+    ///
+    /// [`Algorithm::Vectorised`](super::Algorithm::Vectorised) relies on it to assign
+    /// observations from the matrix of squared euclidean distances.
+    fn is_euclidean(&self) -> bool {
+        false
+    }
+}
+
+fn check_lengths<A>(
+    a: &ArrayBase<impl Data<Elem = A>, Ix1>,
+    b: &ArrayBase<impl Data<Elem = A>, Ix1>,
+) {
+    assert_eq!(
+        a.len(),
+        b.len(),
+        "The two vectors must have the same length"
+    );
+}
+
+/// d(a, b) = sqrt[ (a₁ - b₁)² + ... + (aₙ - bₙ)²]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Euclidean;
+
+impl Distance for Euclidean {
+    fn distance<A: Float>(
+        &self,
+        a: &ArrayBase<impl Data<Elem = A>, Ix1>,
+        b: &ArrayBase<impl Data<Elem = A>, Ix1>,
+    ) -> A {
+        SquaredEuclidean.distance(a, b).sqrt()
+    }
+
+    fn cost<A: Float>(&self, distance: A) -> A {
+        distance.powi(2)
+    }
+
+    fn is_euclidean(&self) -> bool {
+        true
+    }
+}
+
+/// d(a, b) = (a₁ - b₁)² + ... + (aₙ - bₙ)²
+///
+/// It assigns observations exactly like [`Euclidean`], saving a square root,
+/// but it does not satisfy the triangle inequality.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SquaredEuclidean;
+
+impl Distance for SquaredEuclidean {
+    fn distance<A: Float>(
+        &self,
+        a: &ArrayBase<impl Data<Elem = A>, Ix1>,
+        b: &ArrayBase<impl Data<Elem = A>, Ix1>,
+    ) -> A {
+        check_lengths(a, b);
+        a.iter().zip(b.iter()).map(|(&a, &b)| (a - b).powi(2)).sum()
+    }
+
+    fn satisfies_triangle_inequality(&self) -> bool {
+        false
+    }
+
+    fn is_euclidean(&self) -> bool {
+        true
+    }
+}
+
+/// d(a, b) = |a₁ - b₁| + ... + |aₙ - bₙ|
+///
+/// Centroids are updated to the median of their cluster (k-medians).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Manhattan;
+
+impl Distance for Manhattan {
+    fn distance<A: Float>(
+        &self,
+        a: &ArrayBase<impl Data<Elem = A>, Ix1>,
+        b: &ArrayBase<impl Data<Elem = A>, Ix1>,
+    ) -> A {
+        check_lengths(a, b);
+        a.iter().zip(b.iter()).map(|(&a, &b)| (a - b).abs()).sum()
+    }
+
+    fn centre(&self) -> Centre {
+        Centre::Median
+    }
+}
+
+/// d(a, b) = max(|a₁ - b₁|, ..., |aₙ - bₙ|)
+///
+/// There is no closed form for the centre minimising the Chebyshev distance:
+/// centroids are updated to the mean of their cluster.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Chebyshev;
+
+impl Distance for Chebyshev {
+    fn distance<A: Float>(
+        &self,
+        a: &ArrayBase<impl Data<Elem = A>, Ix1>,
+        b: &ArrayBase<impl Data<Elem = A>, Ix1>,
+    ) -> A {
+        check_lengths(a, b);
+        a.iter()
+            .zip(b.iter())
+            .map(|(&a, &b)| (a - b).abs())
+            .fold(A::zero(), A::max)
+    }
+}
+
+/// d(a, b) = (|a₁ - b₁|ᵖ + ... + |aₙ - bₙ|ᵖ)^(1/p)
+///
+/// `Minkowski::new(1.)` is the Manhattan distance, `Minkowski::new(2.)` the euclidean one.
+/// Centroids are updated to the mean of their cluster.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Minkowski(f64);
+
+impl Minkowski {
+    /// The Minkowski distance of order `p`, which must be a positive (finite) number.
+    pub fn new(p: f64) -> Result<Self, KMeansError> {
+        if p.is_finite() && p > 0. {
+            Ok(Minkowski(p))
+        } else {
+            Err(KMeansError::InvalidMinkowskiOrder(p))
+        }
+    }
+
+    /// The order of the distance.
+    pub fn p(&self) -> f64 {
+        self.0
+    }
+}
+
+impl Distance for Minkowski {
+    fn distance<A: Float>(
+        &self,
+        a: &ArrayBase<impl Data<Elem = A>, Ix1>,
+        b: &ArrayBase<impl Data<Elem = A>, Ix1>,
+    ) -> A {
+        check_lengths(a, b);
+        let p = A::from_f64(self.0).expect("Failed to convert f64 to float");
+        a.iter()
+            .zip(b.iter())
+            .map(|(&a, &b)| (a - b).abs().powf(p))
+            .sum::<A>()
+            .powf(A::one() / p)
+    }
+
+    /// Only for `p >= 1`: below that it is not a metric.
+    fn satisfies_triangle_inequality(&self) -> bool {
+        self.0 >= 1.
+    }
+}
+
+/// d(a, b) = 1 - (a · b) / (|a| |b|)
+///
+/// It only looks at the angle between `a` and `b`, ignoring their lengths.
+/// Centroids are updated to the normalised mean of their cluster (spherical k-means).
+/// The distance from a zero vector is defined to be 1.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Cosine;
+
+impl Distance for Cosine {
+    fn distance<A: Float>(
+        &self,
+        a: &ArrayBase<impl Data<Elem = A>, Ix1>,
+        b: &ArrayBase<impl Data<Elem = A>, Ix1>,
+    ) -> A {
+        check_lengths(a, b);
+        let norms = a.dot(a).sqrt() * b.dot(b).sqrt();
+        if norms == A::zero() {
+            return A::one();
+        }
+        A::one() - a.dot(b) / norms
+    }
+
+    fn centre(&self) -> Centre {
+        Centre::NormalisedMean
+    }
+
+    fn satisfies_triangle_inequality(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use ndarray::array;
+
+    #[test]
+    fn distance_oracles() {
+        let a = array![0., 1., 4., 2.];
+        let b = array![1., 1., 2., 4.];
+
+        assert_eq!(Euclidean.distance(&a, &b), 3.);
+        assert_eq!(SquaredEuclidean.distance(&a, &b), 9.);
+        assert_eq!(Manhattan.distance(&a, &b), 5.);
+        assert_eq!(Chebyshev.distance(&a, &b), 2.);
+        let minkowski = |p| Minkowski::new(p).unwrap();
+        assert_abs_diff_eq!(minkowski(2.).distance(&a, &b), 3., epsilon = 1e-12);
+        assert_abs_diff_eq!(minkowski(1.).distance(&a, &b), 5., epsilon = 1e-12);
+        assert_abs_diff_eq!(
+            Cosine.distance(&a, &b),
+            1. - 17. / (21_f64.sqrt() * 22_f64.sqrt()),
+            epsilon = 1e-12
+        );
+        assert_eq!(
+            Manhattan.distance(&a.mapv(|x| x as f32), &b.mapv(|x| x as f32)),
+            5.
+        );
+    }
+
+    #[test]
+    fn minkowski_orders_must_be_positive() {
+        assert_eq!(Minkowski::new(0.5).unwrap().p(), 0.5);
+        for &p in &[0., -1., f64::NAN, f64::INFINITY] {
+            match Minkowski::new(p) {
+                Err(KMeansError::InvalidMinkowskiOrder(q)) => {
+                    assert!(q == p || (q.is_nan() && p.is_nan()))
+                }
+                other => panic!("Expected an error for p = {}, got {:?}", p, other),
+            }
+        }
+    }
+
+    #[test]
+    fn cosine_ignores_lengths() {
+        let a = array![1., 2.];
+
+        assert_abs_diff_eq!(Cosine.distance(&a, &(&a * 10.)), 0., epsilon = 1e-12);
+        assert_abs_diff_eq!(Cosine.distance(&a, &array![-2., 1.]), 1., epsilon = 1e-12);
+        assert_abs_diff_eq!(Cosine.distance(&a, &(&a * -1.)), 2., epsilon = 1e-12);
+        assert_eq!(Cosine.distance(&a, &array![0., 0.]), 1.);
+    }
+
+    #[test]
+    #[should_panic]
+    fn different_lengths() {
+        Manhattan.distance(&array![0., 1., 2.], &array![0., 1., 2., 3.]);
+    }
+}
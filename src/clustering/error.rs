@@ -0,0 +1,109 @@
+use std::error::Error;
+use std::fmt;
+
+/// What can go wrong when fitting a K-means model.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KMeansError {
+    /// We need to look for at least one cluster.
+    ZeroClusters,
+    /// We need to run the algorithm at least once.
+    ZeroInitialisations,
+    /// Mini-batches need at least one observation.
+    ZeroBatchSize,
+    /// We need at least one iteration to refine the initial centroids.
+    ZeroIterations,
+    /// The tolerance must be a non-negative number.
+    InvalidTolerance(f64),
+    /// No observation was assigned to the cluster with this index,
+    /// with [`EmptyClusterPolicy::Error`](super::EmptyClusterPolicy::Error).
+    EmptyCluster(usize),
+    /// Each cluster needs at least one observation to be initialised.
+    NotEnoughObservations {
+        n_observations: usize,
+        n_clusters: usize,
+    },
+    /// There must be exactly one sample weight for each observation.
+    SampleWeightLengthMismatch {
+        n_observations: usize,
+        n_weights: usize,
+    },
+    /// Sample weights must be positive (finite) numbers.
+    InvalidSampleWeight(f64),
+    /// Model selection needs at least two values of `k` to compare, all of them positive.
+    InvalidClusterRange { min_k: usize, max_k: usize },
+    /// The gap statistic needs at least one reference dataset.
+    ZeroReferenceDatasets,
+    /// k-means|| needs at least one sampling round.
+    ZeroRounds,
+    /// The oversampling factor of k-means|| must be a positive (finite) number.
+    InvalidOversamplingFactor(f64),
+    /// The order `p` of a [`Minkowski`](super::distance::Minkowski) distance
+    /// must be a positive (finite) number.
+    InvalidMinkowskiOrder(f64),
+}
+
+impl fmt::Display for KMeansError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KMeansError::ZeroClusters => write!(f, "The number of clusters must be positive"),
+            KMeansError::ZeroInitialisations => {
+                write!(f, "The number of initialisations must be positive")
+            }
+            KMeansError::ZeroBatchSize => write!(f, "The batch size must be positive"),
+            KMeansError::ZeroIterations => {
+                write!(f, "The maximum number of iterations must be positive")
+            }
+            KMeansError::InvalidTolerance(tolerance) => write!(
+                f,
+                "The tolerance must be a non-negative number, got {}",
+                tolerance
+            ),
+            KMeansError::EmptyCluster(cluster_index) => write!(
+                f,
+                "No observation was assigned to cluster {}",
+                cluster_index
+            ),
+            KMeansError::NotEnoughObservations {
+                n_observations,
+                n_clusters,
+            } => write!(
+                f,
+                "Cannot look for {} clusters in {} observations",
+                n_clusters, n_observations
+            ),
+            KMeansError::SampleWeightLengthMismatch {
+                n_observations,
+                n_weights,
+            } => write!(
+                f,
+                "Got {} sample weights for {} observations",
+                n_weights, n_observations
+            ),
+            KMeansError::InvalidSampleWeight(weight) => {
+                write!(f, "Sample weights must be positive numbers, got {}", weight)
+            }
+            KMeansError::InvalidClusterRange { min_k, max_k } => write!(
+                f,
+                "Cannot compare numbers of clusters from {} to {}: \
+                 we need at least two of them, all positive",
+                min_k, max_k
+            ),
+            KMeansError::ZeroReferenceDatasets => {
+                write!(f, "The number of reference datasets must be positive")
+            }
+            KMeansError::ZeroRounds => write!(f, "The number of k-means|| rounds must be positive"),
+            KMeansError::InvalidOversamplingFactor(factor) => write!(
+                f,
+                "The oversampling factor must be a positive number, got {}",
+                factor
+            ),
+            KMeansError::InvalidMinkowskiOrder(p) => write!(
+                f,
+                "The order of a Minkowski distance must be a positive number, got {}",
+                p
+            ),
+        }
+    }
+}
+
+impl Error for KMeansError {}
@@ -0,0 +1,31 @@
+use ndarray::{LinalgScalar, ScalarOperand};
+use num_traits::FromPrimitive;
+use std::fmt::{Debug, Display};
+use std::iter::Sum;
+use std::ops::{AddAssign, DivAssign, MulAssign, SubAssign};
+
+/// The floating point types our clustering routines can work with: `f32` and `f64`.
+///
+/// It bundles together everything we need from an array element, from arithmetic
+/// (`num_traits::Float`) to `ndarray`'s array-scalar operations (`ScalarOperand`),
+/// so that generic signatures can stay as short as `A: Float`.
+pub trait Float:
+    num_traits::Float
+    + FromPrimitive
+    + ScalarOperand
+    + LinalgScalar
+    + AddAssign
+    + SubAssign
+    + MulAssign
+    + DivAssign
+    + Sum
+    + Send
+    + Sync
+    + Debug
+    + Display
+    + 'static
+{
+}
+
+impl Float for f32 {}
+impl Float for f64 {}
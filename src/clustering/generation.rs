@@ -0,0 +1,668 @@
+//! Synthetic datasets to track and benchmark our clustering efforts.
+//!
+//! [`generate_dataset`] is the one we build in the koans: round, equally sized clusters.
+//! Real data is rarely that kind to K-means - [`Blobs`] generates clusters of different
+//! sizes and shapes, with outliers sprinkled on top, while [`generate_moons`],
+//! [`generate_circles`] and [`generate_swiss_roll`] produce shapes K-means is known
+//! to struggle with.
+//!
+//! All generators but [`generate_cluster`] and [`generate_dataset`] return the ground-truth
+//! label of each observation alongside the observations themselves: see
+//! [`generate_labelled_dataset`] for a labelled version of the latter.
+//! They store the observations of each cluster in a contiguous block of rows:
+//! [`shuffle_observations`] interleaves them, so that the row order does not give
+//! the clusters away.
+use super::initialisation::sample_indices;
+use ndarray::{s, stack, Array, Array1, Array2, ArrayView1, ArrayView2, Axis, Zip};
+use ndarray_rand::rand::Rng;
+use ndarray_rand::rand_distr::{StandardNormal, Uniform};
+use ndarray_rand::RandomExt;
+use std::f64::consts::PI;
+
+/// Generate `n_observations` points normally distributed (with unit variance) around `centroid`.
+///
+/// The output has shape `(n_observations, n_features)`, where `n_features` is the length of
+/// `centroid`.
+pub fn generate_cluster(
+    n_observations: usize,
+    centroid: ArrayView1<f64>,
+    rng: &mut impl Rng,
+) -> Array2<f64> {
+    let shape = (n_observations, centroid.len());
+    let origin_cluster: Array2<f64> = Array::random_using(shape, StandardNormal, rng);
+    origin_cluster + centroid.broadcast(shape).expect("Failed to broadcast")
+}
+
+/// Generate a cluster of `cluster_size` observations around each row of `centroids`,
+/// a `(n_centroids, n_features)` matrix.
+///
+/// The output has shape `(n_centroids * cluster_size, n_features)`: the observations of the
+/// i-th cluster are stored in the i-th block of `cluster_size` rows.
+pub fn generate_dataset(
+    cluster_size: usize,
+    centroids: ArrayView2<f64>,
+    rng: &mut impl Rng,
+) -> Array2<f64> {
+    let (n_centroids, n_features) = centroids.dim();
+    let mut dataset: Array2<f64> = Array2::zeros((n_centroids * cluster_size, n_features));
+
+    for (cluster_index, centroid) in centroids.genrows().into_iter().enumerate() {
+        let cluster = generate_cluster(cluster_size, centroid, rng);
+
+        let indexes = s![
+            cluster_index * cluster_size..(cluster_index + 1) * cluster_size,
+            ..
+        ];
+        dataset.slice_mut(indexes).assign(&cluster);
+    }
+    dataset
+}
+
+/// Like [`generate_dataset`], returning the label of each observation as well:
+/// the index of the row of `centroids` it was generated around.
+///
+/// If `shuffle` is `true`, the rows are shuffled using `rng`, after generating the observations:
+/// see [`shuffle_observations`]. Otherwise, the observations are the same as
+/// [`generate_dataset`]'s, given the same `rng`.
+///
+/// ```
+/// use koans::clustering::generate_labelled_dataset;
+/// use ndarray::array;
+/// use ndarray_rand::rand::SeedableRng;
+/// use rand_isaac::Isaac64Rng;
+///
+/// let mut rng = Isaac64Rng::seed_from_u64(42);
+/// let centroids = array![[10., 10.], [1., 12.], [20., 30.]];
+/// let (dataset, labels) = generate_labelled_dataset(100, centroids.view(), true, &mut rng);
+///
+/// assert_eq!(dataset.dim(), (300, 2));
+/// assert_eq!(labels.iter().filter(|&&label| label == 2).count(), 100);
+/// ```
+pub fn generate_labelled_dataset(
+    cluster_size: usize,
+    centroids: ArrayView2<f64>,
+    shuffle: bool,
+    rng: &mut impl Rng,
+) -> (Array2<f64>, Array1<usize>) {
+    let dataset = generate_dataset(cluster_size, centroids, rng);
+    let labels = Array1::from_shape_fn(dataset.nrows(), |index| index / cluster_size);
+    if shuffle {
+        shuffle_observations(dataset.view(), labels.view(), rng)
+    } else {
+        (dataset, labels)
+    }
+}
+
+/// Shuffle the rows of `observations`, a `(n_observations, n_features)` matrix,
+/// together with their `labels`.
+///
+/// # Panics
+///
+/// If there is not one label for each observation.
+pub fn shuffle_observations(
+    observations: ArrayView2<f64>,
+    labels: ArrayView1<usize>,
+    rng: &mut impl Rng,
+) -> (Array2<f64>, Array1<usize>) {
+    assert_eq!(
+        observations.nrows(),
+        labels.len(),
+        "There must be one label for each observation"
+    );
+    // Rather than `SliceRandom::shuffle`: see `sample_indices`
+    let permutation = sample_indices(labels.len(), labels.len(), rng);
+    (
+        observations.select(Axis(0), &permutation),
+        labels.select(Axis(0), &permutation),
+    )
+}
+
+/// How the observations of a cluster are spread around its centroid.
+#[derive(Debug, Clone)]
+enum Spread {
+    /// The same standard deviation along every feature, no correlation.
+    StdDev(f64),
+    /// The lower triangular Cholesky factor `L` of the covariance matrix, `LLᵀ`.
+    Cholesky(Array2<f64>),
+}
+
+/// Normally distributed clusters with a configurable size and shape,
+/// with a builder-like API.
+///
+/// ```
+/// use koans::clustering::generation::Blobs;
+/// use ndarray::array;
+/// use ndarray_rand::rand::SeedableRng;
+/// use rand_isaac::Isaac64Rng;
+///
+/// let mut rng = Isaac64Rng::seed_from_u64(42);
+/// let (observations, labels) = Blobs::new(array![[0., 0.], [10., 10.], [-10., 10.]])
+///     .cluster_sizes(vec![100, 50, 10])
+///     .std_devs(vec![1., 0.5, 3.])
+///     .n_outliers(5)
+///     .generate(&mut rng);
+///
+/// assert_eq!(observations.dim(), (165, 2));
+/// // Outliers are labelled with one past the last cluster
+/// assert_eq!(labels.iter().filter(|&&label| label == 3).count(), 5);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Blobs {
+    centroids: Array2<f64>,
+    cluster_sizes: Vec<usize>,
+    spreads: Vec<Spread>,
+    n_outliers: usize,
+}
+
+impl Blobs {
+    /// A cluster around each row of `centroids`, a `(n_clusters, n_features)` matrix,
+    /// using the default configuration:
+    /// - `cluster_size`: 100;
+    /// - `std_dev`: 1;
+    /// - `n_outliers`: 0.
+    pub fn new(centroids: Array2<f64>) -> Self {
+        let n_clusters = centroids.nrows();
+        Self {
+            centroids,
+            cluster_sizes: vec![100; n_clusters],
+            spreads: vec![Spread::StdDev(1.); n_clusters],
+            n_outliers: 0,
+        }
+    }
+
+    /// The number of observations in each cluster.
+    pub fn cluster_size(mut self, cluster_size: usize) -> Self {
+        self.cluster_sizes = vec![cluster_size; self.centroids.nrows()];
+        self
+    }
+
+    /// The number of observations in each cluster, one for each centroid.
+    ///
+    /// # Panics
+    ///
+    /// If there is not one size for each centroid.
+    pub fn cluster_sizes(mut self, cluster_sizes: Vec<usize>) -> Self {
+        assert_eq!(
+            cluster_sizes.len(),
+            self.centroids.nrows(),
+            "There must be one cluster size for each centroid"
+        );
+        self.cluster_sizes = cluster_sizes;
+        self
+    }
+
+    /// The standard deviation of every cluster, the same along every feature.
+    ///
+    /// # Panics
+    ///
+    /// If `std_dev` is negative or not finite.
+    pub fn std_dev(self, std_dev: f64) -> Self {
+        let n_clusters = self.centroids.nrows();
+        self.std_devs(vec![std_dev; n_clusters])
+    }
+
+    /// The standard deviation of each cluster, the same along every feature.
+    ///
+    /// # Panics
+    ///
+    /// If there is not one standard deviation for each centroid,
+    /// or if any of them is negative or not finite.
+    pub fn std_devs(mut self, std_devs: Vec<f64>) -> Self {
+        assert_eq!(
+            std_devs.len(),
+            self.centroids.nrows(),
+            "There must be one standard deviation for each centroid"
+        );
+        assert!(
+            std_devs.iter().all(|s| s.is_finite() && *s >= 0.),
+            "Standard deviations must be finite and non-negative"
+        );
+        self.spreads = std_devs.into_iter().map(Spread::StdDev).collect();
+        self
+    }
+
+    /// The covariance matrix of each cluster, `(n_features, n_features)`:
+    /// use it to generate elongated, tilted clusters.
+    ///
+    /// # Panics
+    ///
+    /// If there is not one covariance matrix for each centroid,
+    /// or if any of them is not symmetric and positive definite.
+    pub fn covariances(mut self, covariances: Vec<Array2<f64>>) -> Self {
+        assert_eq!(
+            covariances.len(),
+            self.centroids.nrows(),
+            "There must be one covariance matrix for each centroid"
+        );
+        let n_features = self.centroids.ncols();
+        self.spreads = covariances
+            .into_iter()
+            .map(|covariance| {
+                assert_eq!(
+                    covariance.dim(),
+                    (n_features, n_features),
+                    "Covariance matrices must be (n_features, n_features)"
+                );
+                assert!(
+                    covariance == covariance.t(),
+                    "Covariance matrices must be symmetric"
+                );
+                let factor =
+                    cholesky(&covariance).expect("Covariance matrices must be positive definite");
+                Spread::Cholesky(factor)
+            })
+            .collect();
+        self
+    }
+
+    /// How many outliers to add to the clusters: see [`add_outliers`].
+    pub fn n_outliers(mut self, n_outliers: usize) -> Self {
+        self.n_outliers = n_outliers;
+        self
+    }
+
+    /// Generate the observations, a `(n_observations, n_features)` matrix,
+    /// and the label of each one of them.
+    ///
+    /// The observations of the i-th cluster, labelled `i`, are stored in the i-th block of rows,
+    /// followed by the outliers.
+    /// With the default configuration, the observations are the same as
+    /// [`generate_dataset`]'s, given the same `rng`.
+    pub fn generate(&self, rng: &mut impl Rng) -> (Array2<f64>, Array1<usize>) {
+        let n_features = self.centroids.ncols();
+        let clusters: Vec<Array2<f64>> = self
+            .centroids
+            .genrows()
+            .into_iter()
+            .zip(&self.cluster_sizes)
+            .zip(&self.spreads)
+            .map(|((centroid, &cluster_size), spread)| {
+                let shape = (cluster_size, n_features);
+                let origin_cluster: Array2<f64> = Array::random_using(shape, StandardNormal, rng);
+                let cluster = match spread {
+                    Spread::StdDev(std_dev) => origin_cluster * *std_dev,
+                    // If z ~ N(0, I), then Lz ~ N(0, LLᵀ)
+                    Spread::Cholesky(factor) => origin_cluster.dot(&factor.t()),
+                };
+                cluster + centroid.broadcast(shape).expect("Failed to broadcast")
+            })
+            .collect();
+        let observations = if clusters.is_empty() {
+            Array2::zeros((0, n_features))
+        } else {
+            let views: Vec<_> = clusters.iter().map(|cluster| cluster.view()).collect();
+            stack(Axis(0), &views).expect("All clusters have the same number of features")
+        };
+        let mut labels = Array1::zeros(observations.nrows());
+        let mut offset = 0;
+        for (label, &cluster_size) in self.cluster_sizes.iter().enumerate() {
+            labels
+                .slice_mut(s![offset..offset + cluster_size])
+                .fill(label);
+            offset += cluster_size;
+        }
+
+        if self.n_outliers == 0 {
+            return (observations, labels);
+        }
+        let (observations, mut labels) =
+            add_outliers(observations.view(), labels.view(), self.n_outliers, rng);
+        // Empty trailing clusters should not shift the outlier label
+        let n_clusters = self.centroids.nrows();
+        let n_observations = labels.len();
+        labels
+            .slice_mut(s![n_observations - self.n_outliers..])
+            .fill(n_clusters);
+        (observations, labels)
+    }
+}
+
+/// Append `n_outliers` observations drawn uniformly at random within the bounding box
+/// of `observations`, a `(n_observations, n_features)` matrix.
+///
+/// Outliers are labelled with one past the largest label in `labels`.
+///
+/// # Panics
+///
+/// If `observations` is empty or if there is not one label for each observation.
+pub fn add_outliers(
+    observations: ArrayView2<f64>,
+    labels: ArrayView1<usize>,
+    n_outliers: usize,
+    rng: &mut impl Rng,
+) -> (Array2<f64>, Array1<usize>) {
+    assert!(
+        observations.nrows() > 0,
+        "Outliers are drawn within the bounding box of the observations: there must be at least one"
+    );
+    assert_eq!(
+        observations.nrows(),
+        labels.len(),
+        "There must be one label for each observation"
+    );
+    let (mins, maxs) = (
+        observations.fold_axis(Axis(0), f64::INFINITY, |a, &b| a.min(b)),
+        observations.fold_axis(Axis(0), f64::NEG_INFINITY, |a, &b| a.max(b)),
+    );
+    let mut outliers: Array2<f64> = Array::random_using(
+        (n_outliers, observations.ncols()),
+        Uniform::new(0., 1.),
+        rng,
+    );
+    for mut outlier in outliers.genrows_mut() {
+        Zip::from(&mut outlier)
+            .and(&mins)
+            .and(&maxs)
+            .apply(|x, &min, &max| *x = min + *x * (max - min));
+    }
+
+    let outlier_label = labels.iter().max().map_or(0, |&label| label + 1);
+    let observations = stack(Axis(0), &[observations.view(), outliers.view()])
+        .expect("Outliers have the same number of features");
+    let outlier_labels = Array1::from_elem(n_outliers, outlier_label);
+    let labels =
+        stack(Axis(0), &[labels.view(), outlier_labels.view()]).expect("Failed to stack labels");
+    (observations, labels)
+}
+
+/// Two interleaving half circles in the plane, with normally distributed noise
+/// (of standard deviation `noise`) added to each observation.
+///
+/// The upper half circle (labelled `0`) has `n_observations / 2` observations,
+/// the lower one (labelled `1`) the rest. The output has shape `(n_observations, 2)`.
+pub fn generate_moons(
+    n_observations: usize,
+    noise: f64,
+    rng: &mut impl Rng,
+) -> (Array2<f64>, Array1<usize>) {
+    let n_upper = n_observations / 2;
+    let n_lower = n_observations - n_upper;
+    // Evenly spaced angles between 0 and π, both included
+    let angles = |n: usize| Array::linspace(0., PI, n);
+
+    let mut observations = Array2::zeros((n_observations, 2));
+    for (mut observation, angle) in observations.genrows_mut().into_iter().zip(
+        angles(n_upper).iter().map(|t| (t.cos(), t.sin())).chain(
+            angles(n_lower)
+                .iter()
+                .map(|t| (1. - t.cos(), 0.5 - t.sin())),
+        ),
+    ) {
+        observation[0] = angle.0;
+        observation[1] = angle.1;
+    }
+    let labels = two_labels(n_upper, n_lower);
+    (with_noise(observations, noise, rng), labels)
+}
+
+/// Two concentric circles in the plane, with normally distributed noise
+/// (of standard deviation `noise`) added to each observation.
+///
+/// The outer circle (labelled `0`) has radius 1 and `n_observations / 2` observations,
+/// the inner one (labelled `1`) has radius `factor` and the rest.
+/// The output has shape `(n_observations, 2)`.
+///
+/// # Panics
+///
+/// If `factor` is not between 0 and 1 (excluded).
+pub fn generate_circles(
+    n_observations: usize,
+    factor: f64,
+    noise: f64,
+    rng: &mut impl Rng,
+) -> (Array2<f64>, Array1<usize>) {
+    assert!(
+        factor > 0. && factor < 1.,
+        "The inner circle must be smaller than the outer one: `factor` must be between 0 and 1"
+    );
+    let n_outer = n_observations / 2;
+    let n_inner = n_observations - n_outer;
+    // Evenly spaced angles, without counting 0 and 2π twice
+    let circle = |n: usize, radius: f64| {
+        (0..n).map(move |i| {
+            let angle = 2. * PI * i as f64 / n as f64;
+            (radius * angle.cos(), radius * angle.sin())
+        })
+    };
+
+    let mut observations = Array2::zeros((n_observations, 2));
+    for (mut observation, point) in observations
+        .genrows_mut()
+        .into_iter()
+        .zip(circle(n_outer, 1.).chain(circle(n_inner, factor)))
+    {
+        observation[0] = point.0;
+        observation[1] = point.1;
+    }
+    let labels = two_labels(n_outer, n_inner);
+    (with_noise(observations, noise, rng), labels)
+}
+
+/// A swiss roll: a rectangle rolled up into a spiral, in three dimensions,
+/// with normally distributed noise (of standard deviation `noise`) added to each observation.
+///
+/// The roll is cut into `n_segments` strips of the same length, from the centre of the spiral
+/// outwards: each observation is labelled with the index of its strip.
+/// The output has shape `(n_observations, 3)`.
+///
+/// # Panics
+///
+/// If `n_segments` is 0.
+pub fn generate_swiss_roll(
+    n_observations: usize,
+    n_segments: usize,
+    noise: f64,
+    rng: &mut impl Rng,
+) -> (Array2<f64>, Array1<usize>) {
+    assert!(n_segments > 0, "There must be at least one segment");
+    let positions: Array1<f64> = Array::random_using(n_observations, Uniform::new(0., 1.), rng);
+    let heights: Array1<f64> = Array::random_using(n_observations, Uniform::new(0., 21.), rng);
+
+    let mut observations = Array2::zeros((n_observations, 3));
+    for ((mut observation, &position), &height) in observations
+        .genrows_mut()
+        .into_iter()
+        .zip(&positions)
+        .zip(&heights)
+    {
+        // Between one and a half and four and a half turns
+        let t = 1.5 * PI * (1. + 2. * position);
+        observation[0] = t * t.cos();
+        observation[1] = height;
+        observation[2] = t * t.sin();
+    }
+    let labels =
+        positions.mapv(|position| ((position * n_segments as f64) as usize).min(n_segments - 1));
+    (with_noise(observations, noise, rng), labels)
+}
+
+/// `n_first` zeros followed by `n_second` ones.
+fn two_labels(n_first: usize, n_second: usize) -> Array1<usize> {
+    let mut labels = Array1::zeros(n_first + n_second);
+    labels.slice_mut(s![n_first..]).fill(1);
+    labels
+}
+
+/// Add normally distributed noise, with standard deviation `noise`, to each feature.
+fn with_noise(observations: Array2<f64>, noise: f64, rng: &mut impl Rng) -> Array2<f64> {
+    let perturbations: Array2<f64> = Array::random_using(observations.dim(), StandardNormal, rng);
+    observations + perturbations * noise
+}
+
+/// The lower triangular matrix `L` such that `LLᵀ` is equal to `matrix`,
+/// if `matrix` is positive definite.
+fn cholesky(matrix: &Array2<f64>) -> Option<Array2<f64>> {
+    let n = matrix.nrows();
+    let mut factor = Array2::zeros((n, n));
+    for i in 0..n {
+        for j in 0..=i {
+            let dot: f64 = (0..j).map(|k| factor[[i, k]] * factor[[j, k]]).sum();
+            if i == j {
+                let diagonal = matrix[[i, i]] - dot;
+                if diagonal <= 0. || !diagonal.is_finite() {
+                    return None;
+                }
+                factor[[i, i]] = diagonal.sqrt();
+            } else {
+                factor[[i, j]] = (matrix[[i, j]] - dot) / factor[[j, j]];
+            }
+        }
+    }
+    Some(factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+    use ndarray::{array, Axis};
+    use ndarray_rand::rand::SeedableRng;
+    use rand_isaac::Isaac64Rng;
+
+    #[test]
+    fn clusters_are_centred_around_their_centroid() {
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        let centroid = array![10., 10.];
+        let cluster = generate_cluster(20000, centroid.view(), &mut rng);
+
+        assert_abs_diff_eq!(cluster.mean_axis(Axis(0)).unwrap(), centroid, epsilon = 0.1);
+        assert_abs_diff_eq!(cluster.var_axis(Axis(0), 1.), array![1., 1.], epsilon = 0.1);
+    }
+
+    #[test]
+    fn datasets_are_reproducible() {
+        let centroids = array![[10., 10.], [1., 12.], [20., 30.], [-20., 30.]];
+        let n = 1000;
+
+        let a = generate_dataset(n, centroids.view(), &mut Isaac64Rng::seed_from_u64(42));
+        let b = generate_dataset(n, centroids.view(), &mut Isaac64Rng::seed_from_u64(42));
+
+        assert_eq!(a.dim(), (4 * n, 2));
+        assert_eq!(a, b);
+        assert!(a.genrows().into_iter().all(|r| r != array![0., 0.]));
+    }
+
+    #[test]
+    fn default_blobs_match_generate_dataset() {
+        let centroids = array![[10., 10.], [1., 12.], [20., 30.]];
+
+        let (observations, labels) = Blobs::new(centroids.clone())
+            .cluster_size(50)
+            .generate(&mut Isaac64Rng::seed_from_u64(42));
+
+        let dataset = generate_dataset(50, centroids.view(), &mut Isaac64Rng::seed_from_u64(42));
+        assert_eq!(observations, dataset);
+        assert_eq!(labels.slice(s![..50]), Array1::<usize>::zeros(50));
+        assert_eq!(labels.slice(s![100..]), Array1::from_elem(50, 2));
+    }
+
+    #[test]
+    fn blobs_have_the_requested_shape() {
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        let covariance = array![[4., 1.5], [1.5, 1.]];
+        let (observations, labels) = Blobs::new(array![[0., 0.], [50., 50.]])
+            .cluster_sizes(vec![20000, 10])
+            .covariances(vec![covariance.clone(), Array2::eye(2)])
+            .n_outliers(30)
+            .generate(&mut rng);
+
+        assert_eq!(observations.dim(), (20040, 2));
+        assert_eq!(labels.iter().filter(|&&label| label == 1).count(), 10);
+        assert_eq!(labels.slice(s![-30..]), Array1::from_elem(30, 2));
+        let cluster = observations.slice(s![..20000, ..]);
+        let centred = &cluster - &cluster.mean_axis(Axis(0)).unwrap();
+        let sample_covariance = centred.t().dot(&centred) / 19999.;
+        assert_abs_diff_eq!(sample_covariance, covariance, epsilon = 0.1);
+        // Outliers stay within the bounding box
+        for outlier in observations.slice(s![-30.., ..]).genrows() {
+            assert!(outlier.iter().all(|x| (-10. ..=60.).contains(x)));
+        }
+
+        let (observations, _) = Blobs::new(array![[0.], [10.]])
+            .std_devs(vec![0.5, 3.])
+            .cluster_size(20000)
+            .generate(&mut rng);
+        let std_devs: Vec<f64> = [s![..20000, ..], s![20000.., ..]]
+            .iter()
+            .map(|&block| observations.slice(block).var_axis(Axis(0), 1.)[0].sqrt())
+            .collect();
+        assert_abs_diff_eq!(std_devs[0], 0.5, epsilon = 0.02);
+        assert_abs_diff_eq!(std_devs[1], 3., epsilon = 0.1);
+    }
+
+    #[test]
+    #[should_panic(expected = "positive definite")]
+    fn covariances_must_be_positive_definite() {
+        Blobs::new(array![[0., 0.]]).covariances(vec![array![[1., 2.], [2., 1.]]]);
+    }
+
+    #[test]
+    fn shapes_without_noise() {
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+
+        let (moons, labels) = generate_moons(101, 0., &mut rng);
+        assert_eq!(moons.dim(), (101, 2));
+        assert_eq!(labels.sum(), 51);
+        for (point, &label) in moons.genrows().into_iter().zip(&labels) {
+            let centre = if label == 0 { (0., 0.) } else { (1., 0.5) };
+            let radius = ((point[0] - centre.0).powi(2) + (point[1] - centre.1).powi(2)).sqrt();
+            assert_abs_diff_eq!(radius, 1., epsilon = 1e-12);
+        }
+
+        let (circles, labels) = generate_circles(100, 0.3, 0., &mut rng);
+        for (point, &label) in circles.genrows().into_iter().zip(&labels) {
+            let radius = (point[0].powi(2) + point[1].powi(2)).sqrt();
+            assert_abs_diff_eq!(radius, [1., 0.3][label], epsilon = 1e-12);
+        }
+
+        let (roll, labels) = generate_swiss_roll(1000, 3, 0., &mut rng);
+        assert_eq!(roll.dim(), (1000, 3));
+        for (point, &label) in roll.genrows().into_iter().zip(&labels) {
+            // The distance from the axis of the roll grows from one segment to the next
+            let t = (point[0].powi(2) + point[2].powi(2)).sqrt();
+            let position = (t / (1.5 * PI) - 1.) / 2.;
+            assert_eq!(label, (position * 3.) as usize);
+            assert!((0. ..=21.).contains(&point[1]));
+        }
+
+        // Noise is reproducible
+        let a = generate_moons(50, 0.1, &mut Isaac64Rng::seed_from_u64(7));
+        let b = generate_moons(50, 0.1, &mut Isaac64Rng::seed_from_u64(7));
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn labelled_datasets_can_be_shuffled() {
+        let centroids = array![[10., 10.], [1., 12.], [20., 30.], [-20., 30.]];
+        let n = 50;
+
+        let (dataset, labels) = generate_labelled_dataset(
+            n,
+            centroids.view(),
+            false,
+            &mut Isaac64Rng::seed_from_u64(42),
+        );
+        assert_eq!(
+            dataset,
+            generate_dataset(n, centroids.view(), &mut Isaac64Rng::seed_from_u64(42))
+        );
+        assert_eq!(labels.slice(s![n..2 * n]), Array1::from_elem(n, 1));
+
+        let (shuffled, shuffled_labels) = generate_labelled_dataset(
+            n,
+            centroids.view(),
+            true,
+            &mut Isaac64Rng::seed_from_u64(42),
+        );
+        assert_ne!(shuffled_labels, labels);
+        // The same observations, each one with its own label
+        for (observation, label) in shuffled.genrows().into_iter().zip(&shuffled_labels) {
+            let index = dataset
+                .genrows()
+                .into_iter()
+                .position(|row| row == observation)
+                .unwrap();
+            assert_eq!(labels[index], *label);
+        }
+    }
+}
@@ -0,0 +1,627 @@
+//! How do we choose our initial set of centroids?
+use super::assignment::{closest_centroid, euclidean_distance};
+use super::error::KMeansError;
+use super::Float;
+use ndarray::{Array1, Array2, ArrayBase, ArrayView1, Axis, Data, Ix2};
+use ndarray_rand::rand::distributions::{Distribution, WeightedIndex};
+use ndarray_rand::rand::Rng;
+use std::collections::HashSet;
+
+/// The strategies available to choose the initial set of centroids.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum InitStrategy {
+    /// Pick `n_clusters` distinct observations at random: see [`get_random_centroids`].
+    #[default]
+    Forgy,
+    /// Spread the initial centroids out, sampling each new one with a probability
+    /// proportional to its squared distance from the closest centroid picked so far:
+    /// see [`k_means_plus_plus`].
+    KMeansPlusPlus,
+    /// A scalable variant of k-means++, sampling many candidates per pass over the dataset:
+    /// see [`k_means_parallel`].
+    KMeansParallel {
+        n_rounds: usize,
+        oversampling_factor: f64,
+    },
+}
+
+impl InitStrategy {
+    /// Choose `n_clusters` initial centroids among `observations`,
+    /// a `(n_observations, n_features)` matrix.
+    ///
+    /// # Panics
+    ///
+    /// If `n_clusters` is greater than the number of observations.
+    pub fn initialise<A: Float>(
+        &self,
+        n_clusters: usize,
+        observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+        rng: &mut impl Rng,
+    ) -> Array2<A> {
+        match self {
+            InitStrategy::Forgy => get_random_centroids(n_clusters, observations, rng),
+            InitStrategy::KMeansPlusPlus => k_means_plus_plus(n_clusters, observations, rng),
+            InitStrategy::KMeansParallel {
+                n_rounds,
+                oversampling_factor,
+            } => k_means_parallel(
+                n_clusters,
+                observations,
+                *n_rounds,
+                *oversampling_factor,
+                rng,
+            ),
+        }
+    }
+
+    /// Like [`InitStrategy::initialise`], where each observation is `sample_weight` times
+    /// as likely to be picked as it would be without weights: e.g. the Forgy method picks
+    /// observations with a probability proportional to their weight.
+    ///
+    /// Weights must be non-negative, with a positive sum. Observations with a weight of zero
+    /// are only picked if there are not enough observations with a positive weight.
+    ///
+    /// # Panics
+    ///
+    /// If `n_clusters` is greater than the number of observations or if `sample_weight`
+    /// does not have one weight for each observation.
+    pub fn initialise_weighted<A: Float>(
+        &self,
+        n_clusters: usize,
+        observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+        sample_weight: ArrayView1<f64>,
+        rng: &mut impl Rng,
+    ) -> Array2<A> {
+        assert_eq!(
+            sample_weight.len(),
+            observations.nrows(),
+            "There must be one weight for each observation"
+        );
+        let sample_weight = sample_weight.to_vec();
+        let indices = match self {
+            InitStrategy::Forgy => weighted_forgy(n_clusters, &sample_weight, rng),
+            InitStrategy::KMeansPlusPlus => {
+                weighted_k_means_plus_plus(n_clusters, observations, Some(&sample_weight), rng)
+            }
+            InitStrategy::KMeansParallel {
+                n_rounds,
+                oversampling_factor,
+            } => weighted_k_means_parallel(
+                n_clusters,
+                observations,
+                *n_rounds,
+                *oversampling_factor,
+                Some(&sample_weight),
+                rng,
+            ),
+        };
+        observations.select(Axis(0), &indices)
+    }
+
+    /// Check the parameters of the strategy, before any centroid is picked.
+    pub(crate) fn validate(&self) -> Result<(), KMeansError> {
+        if let InitStrategy::KMeansParallel {
+            n_rounds,
+            oversampling_factor,
+        } = *self
+        {
+            if n_rounds == 0 {
+                return Err(KMeansError::ZeroRounds);
+            }
+            if !oversampling_factor.is_finite() || oversampling_factor <= 0. {
+                return Err(KMeansError::InvalidOversamplingFactor(oversampling_factor));
+            }
+        }
+        Ok(())
+    }
+
+    /// k-means|| with the parameters recommended by its authors:
+    /// 5 rounds, sampling `2 * n_clusters` candidates per round.
+    pub fn k_means_parallel() -> Self {
+        InitStrategy::KMeansParallel {
+            n_rounds: 5,
+            oversampling_factor: 2.,
+        }
+    }
+}
+
+/// Pick `amount` distinct indices in `0..length` uniformly at random, in random order.
+///
+/// It does the same job as `rand::seq::index::sample`, which draws `u32`s when `length` is small
+/// enough: `Isaac64Rng` (through `rand_core` 0.5) produces them with an out-of-bounds unchecked read
+/// that debug builds abort on. We only ever draw `usize`s.
+///
+/// # Panics
+///
+/// If `amount` is greater than `length`.
+pub fn sample_indices(length: usize, amount: usize, rng: &mut impl Rng) -> Vec<usize> {
+    assert!(
+        amount <= length,
+        "Cannot pick {} distinct indices out of {}",
+        amount,
+        length
+    );
+    if amount * 2 <= length {
+        // Rejection sampling: less than `2 * amount` draws on average
+        let mut picked = HashSet::with_capacity(amount);
+        let mut indices = Vec::with_capacity(amount);
+        while indices.len() < amount {
+            let index = rng.gen_range(0, length);
+            if picked.insert(index) {
+                indices.push(index);
+            }
+        }
+        indices
+    } else {
+        // A partial Fisher-Yates shuffle
+        let mut indices: Vec<usize> = (0..length).collect();
+        for i in 0..amount {
+            indices.swap(i, rng.gen_range(i, length));
+        }
+        indices.truncate(amount);
+        indices
+    }
+}
+
+/// The Forgy method: pick `n_clusters` distinct observations as the initial centroids.
+///
+/// `observations` is a `(n_observations, n_features)` matrix.
+///
+/// # Panics
+///
+/// If `n_clusters` is greater than the number of observations.
+pub fn get_random_centroids<A, S>(
+    n_clusters: usize,
+    observations: &ArrayBase<S, Ix2>,
+    rng: &mut impl Rng,
+) -> Array2<A>
+where
+    A: Float,
+    S: Data<Elem = A>,
+{
+    let (n_samples, _) = observations.dim();
+    let indices = sample_indices(n_samples, n_clusters, rng);
+    observations.select(Axis(0), &indices)
+}
+
+/// k-means++ (Arthur and Vassilvitskii, 2007): the first centroid is an observation picked
+/// uniformly at random, each of the following ones is an observation picked with a probability
+/// proportional to its squared distance from the closest centroid chosen so far.
+///
+/// Far-apart centroids are much less likely to converge to a poor local minimum than
+/// the ones chosen by the Forgy method.
+///
+/// `observations` is a `(n_observations, n_features)` matrix.
+///
+/// # Panics
+///
+/// If `n_clusters` is greater than the number of observations.
+pub fn k_means_plus_plus<A, S>(
+    n_clusters: usize,
+    observations: &ArrayBase<S, Ix2>,
+    rng: &mut impl Rng,
+) -> Array2<A>
+where
+    A: Float,
+    S: Data<Elem = A>,
+{
+    let indices = weighted_k_means_plus_plus(n_clusters, observations, None, rng);
+    observations.select(Axis(0), &indices)
+}
+
+/// k-means|| (Bahmani et al., 2012), a variant of k-means++ that scales to large datasets.
+///
+/// k-means++ needs a full pass over `observations` for each centroid it picks.
+/// k-means|| instead performs `n_rounds` passes, each one sampling around
+/// `oversampling_factor * n_clusters` candidates at once, with a probability proportional
+/// to their squared distance from the closest candidate picked so far.
+/// Each candidate is then weighted by the number of observations closest to it and the
+/// candidates are reclustered down to `n_clusters` centroids using weighted k-means++.
+///
+/// `observations` is a `(n_observations, n_features)` matrix.
+///
+/// # Panics
+///
+/// If `n_clusters` is greater than the number of observations.
+pub fn k_means_parallel<A, S>(
+    n_clusters: usize,
+    observations: &ArrayBase<S, Ix2>,
+    n_rounds: usize,
+    oversampling_factor: f64,
+    rng: &mut impl Rng,
+) -> Array2<A>
+where
+    A: Float,
+    S: Data<Elem = A>,
+{
+    let indices = weighted_k_means_parallel(
+        n_clusters,
+        observations,
+        n_rounds,
+        oversampling_factor,
+        None,
+        rng,
+    );
+    observations.select(Axis(0), &indices)
+}
+
+/// The indices of the observations picked by the Forgy method, where the probability of picking
+/// each observation is proportional to its weight.
+fn weighted_forgy(n_clusters: usize, sample_weights: &[f64], rng: &mut impl Rng) -> Vec<usize> {
+    let n_samples = sample_weights.len();
+    assert!(
+        n_clusters <= n_samples,
+        "Cannot pick {} centroids out of {} observations",
+        n_clusters,
+        n_samples
+    );
+    let mut weights = sample_weights.to_vec();
+    let mut indices = Vec::with_capacity(n_clusters);
+    while indices.len() < n_clusters {
+        let next = match WeightedIndex::new(&weights) {
+            Ok(distribution) => distribution.sample(rng),
+            // Only observations with a weight of zero are left: any of them will do.
+            Err(_) => {
+                let candidates: Vec<usize> =
+                    (0..n_samples).filter(|i| !indices.contains(i)).collect();
+                candidates[rng.gen_range(0, candidates.len())]
+            }
+        };
+        // Never pick the same observation twice
+        weights[next] = 0.;
+        indices.push(next);
+    }
+    indices
+}
+
+/// The indices of the observations picked by k-means||, where the probability of sampling each
+/// observation as a candidate is multiplied by its weight (if any).
+fn weighted_k_means_parallel<A, S>(
+    n_clusters: usize,
+    observations: &ArrayBase<S, Ix2>,
+    n_rounds: usize,
+    oversampling_factor: f64,
+    sample_weights: Option<&[f64]>,
+    rng: &mut impl Rng,
+) -> Vec<usize>
+where
+    A: Float,
+    S: Data<Elem = A>,
+{
+    let (n_samples, _) = observations.dim();
+    assert!(
+        n_clusters <= n_samples,
+        "Cannot pick {} centroids out of {} observations",
+        n_clusters,
+        n_samples
+    );
+    if n_clusters == 0 {
+        return vec![];
+    }
+    let weight = |index: usize| sample_weights.map_or(1., |sample_weights| sample_weights[index]);
+
+    let mut candidates = vec![match sample_weights {
+        Some(sample_weights) => WeightedIndex::new(sample_weights)
+            .expect("Sample weights must be non-negative, with a positive sum")
+            .sample(rng),
+        None => rng.gen_range(0, n_samples),
+    }];
+    // The squared distance of each observation from its closest candidate
+    let mut distances: Array1<A> = Array1::from_elem(n_samples, A::infinity());
+    let mut n_updated = 0;
+    for _ in 0..n_rounds {
+        update_distances(&mut distances, observations, &candidates[n_updated..]);
+        n_updated = candidates.len();
+
+        let cost: f64 = match sample_weights {
+            Some(_) => distances
+                .iter()
+                .enumerate()
+                .map(|(index, &distance)| weight(index) * to_f64(distance))
+                .sum(),
+            None => to_f64(distances.sum()),
+        };
+        if cost == 0. {
+            break;
+        }
+        let expected_n_samples = oversampling_factor * n_clusters as f64;
+        for (index, &distance) in distances.iter().enumerate() {
+            if rng.gen::<f64>() < expected_n_samples * weight(index) * to_f64(distance) / cost {
+                candidates.push(index);
+            }
+        }
+    }
+
+    if candidates.len() <= n_clusters {
+        // Not enough candidates to recluster: top them up with the Forgy method.
+        let others: Vec<usize> = (0..n_samples).filter(|i| !candidates.contains(i)).collect();
+        let n_missing = n_clusters - candidates.len();
+        for i in sample_indices(others.len(), n_missing, rng) {
+            candidates.push(others[i]);
+        }
+        return candidates;
+    }
+
+    let candidate_observations = observations.select(Axis(0), &candidates);
+    let mut weights = vec![0.; candidates.len()];
+    for (index, observation) in observations.genrows().into_iter().enumerate() {
+        weights[closest_centroid(&candidate_observations, &observation)] += weight(index);
+    }
+    weighted_k_means_plus_plus(n_clusters, &candidate_observations, Some(&weights), rng)
+        .into_iter()
+        .map(|index| candidates[index])
+        .collect()
+}
+
+/// The indices of the observations picked by k-means++, where the probability of picking each
+/// observation is multiplied by its weight (if any).
+fn weighted_k_means_plus_plus<A, S>(
+    n_clusters: usize,
+    observations: &ArrayBase<S, Ix2>,
+    sample_weights: Option<&[f64]>,
+    rng: &mut impl Rng,
+) -> Vec<usize>
+where
+    A: Float,
+    S: Data<Elem = A>,
+{
+    let (n_samples, _) = observations.dim();
+    assert!(
+        n_clusters <= n_samples,
+        "Cannot pick {} centroids out of {} observations",
+        n_clusters,
+        n_samples
+    );
+    if n_clusters == 0 {
+        return vec![];
+    }
+
+    let first = match sample_weights {
+        Some(sample_weights) => WeightedIndex::new(sample_weights)
+            .expect("Sample weights must be non-negative, with a positive sum")
+            .sample(rng),
+        None => rng.gen_range(0, n_samples),
+    };
+    let mut indices = vec![first];
+    // The squared distance of each observation from its closest centroid
+    let mut distances: Array1<A> = Array1::from_elem(n_samples, A::infinity());
+    while indices.len() < n_clusters {
+        update_distances(&mut distances, observations, &indices[indices.len() - 1..]);
+        let weights = distances.iter().enumerate().map(|(i, &distance)| {
+            let distance = to_f64(distance);
+            sample_weights.map_or(distance, |sample_weights| distance * sample_weights[i])
+        });
+        let next = match WeightedIndex::new(weights) {
+            Ok(distribution) => distribution.sample(rng),
+            // All observations coincide with a centroid: any of the others will do.
+            Err(_) => {
+                let candidates: Vec<usize> =
+                    (0..n_samples).filter(|i| !indices.contains(i)).collect();
+                candidates[rng.gen_range(0, candidates.len())]
+            }
+        };
+        indices.push(next);
+    }
+    indices
+}
+
+/// Lower the squared distance of each observation from its closest centroid, taking
+/// into account the observations at `new_indices` as additional centroids.
+fn update_distances<A, S>(
+    distances: &mut Array1<A>,
+    observations: &ArrayBase<S, Ix2>,
+    new_indices: &[usize],
+) where
+    A: Float,
+    S: Data<Elem = A>,
+{
+    for &index in new_indices {
+        let centroid = observations.row(index);
+        for (distance, observation) in distances.iter_mut().zip(observations.genrows()) {
+            *distance = distance.min(euclidean_distance(&observation, &centroid).powi(2));
+        }
+    }
+}
+
+// `rand`'s weighted sampling works with `f64` weights.
+fn to_f64<A: Float>(x: A) -> f64 {
+    x.to_f64().expect("Failed to convert float to f64")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clustering::assignment::compute_cluster_memberships;
+    use crate::clustering::generation::generate_dataset;
+    use crate::clustering::k_means::inertia;
+    use approx::assert_abs_diff_eq;
+    use ndarray::{array, s, Array, ArrayView1};
+    use ndarray_rand::rand::SeedableRng;
+    use ndarray_rand::rand_distr::{StandardNormal, Uniform};
+    use ndarray_rand::RandomExt;
+    use rand_isaac::Isaac64Rng;
+
+    fn count_in(matrix: &Array2<f64>, row: &ArrayView1<f64>) -> usize {
+        matrix.genrows().into_iter().filter(|r| r == row).count()
+    }
+
+    #[test]
+    fn centroids_are_distinct_observations() {
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        let observations: Array2<f64> = Array::random_using((100, 3), StandardNormal, &mut rng);
+
+        let centroids = get_random_centroids(100, &observations, &mut rng);
+
+        assert!(centroids
+            .genrows()
+            .into_iter()
+            .all(|c| count_in(&observations, &c) == 1 && count_in(&centroids, &c) == 1));
+    }
+
+    #[test]
+    fn sampled_indices_are_distinct() {
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        // Both rejection sampling and the partial shuffle
+        for &(length, amount) in &[(100, 3), (100, 50), (100, 99), (10, 10), (5, 0)] {
+            let mut indices = sample_indices(length, amount, &mut rng);
+            assert_eq!(indices.len(), amount);
+            indices.sort();
+            indices.dedup();
+            assert_eq!(indices.len(), amount);
+            assert!(indices.iter().all(|&index| index < length));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn more_clusters_than_observations() {
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        let observations: Array2<f64> = Array::random_using((4, 3), StandardNormal, &mut rng);
+
+        get_random_centroids(5, &observations, &mut rng);
+    }
+
+    #[test]
+    fn k_means_plus_plus_spreads_centroids_across_clusters() {
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        let expected_centroids = array![[0., 0.], [100., 0.], [0., 100.], [100., 100.]];
+        let observations = generate_dataset(50, expected_centroids.view(), &mut rng);
+
+        let centroids = InitStrategy::KMeansPlusPlus.initialise(4, &observations, &mut rng);
+
+        // Each initial centroid lands in a different cluster
+        for expected in expected_centroids.genrows() {
+            assert_eq!(
+                centroids
+                    .genrows()
+                    .into_iter()
+                    .filter(|c| euclidean_distance(c, &expected) < 10.)
+                    .count(),
+                1
+            );
+        }
+    }
+
+    #[test]
+    fn k_means_plus_plus_handles_duplicate_observations() {
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        let observations = array![[1., 1.], [1., 1.], [1., 1.]];
+
+        let centroids = k_means_plus_plus(3, &observations, &mut rng);
+
+        assert_eq!(centroids, observations);
+    }
+
+    #[test]
+    fn single_precision_observations() {
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        let observations: Array2<f32> = Array::random_using((100, 3), StandardNormal, &mut rng);
+
+        for init in &[
+            InitStrategy::Forgy,
+            InitStrategy::KMeansPlusPlus,
+            InitStrategy::k_means_parallel(),
+        ] {
+            let centroids = init.initialise(5, &observations, &mut rng);
+            assert_eq!(centroids.dim(), (5, 3));
+            assert!(centroids
+                .genrows()
+                .into_iter()
+                .all(|c| observations.genrows().into_iter().any(|o| o == c)));
+        }
+    }
+
+    #[test]
+    fn k_means_parallel_is_reproducible() {
+        let observations: Array2<f64> =
+            Array::random_using((500, 2), StandardNormal, &mut Isaac64Rng::seed_from_u64(42));
+        let init = InitStrategy::k_means_parallel();
+
+        let first = init.initialise(10, &observations, &mut Isaac64Rng::seed_from_u64(7));
+        let second = init.initialise(10, &observations, &mut Isaac64Rng::seed_from_u64(7));
+
+        assert_eq!(first, second);
+        assert_eq!(first.dim(), (10, 2));
+        assert!(first
+            .genrows()
+            .into_iter()
+            .all(|c| count_in(&observations, &c) >= 1 && count_in(&first, &c) == 1));
+    }
+
+    #[test]
+    fn k_means_parallel_is_as_good_as_k_means_plus_plus() {
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        let expected_centroids: Array2<f64> =
+            Array::random_using((20, 2), Uniform::new(-100., 100.), &mut rng);
+        let observations = generate_dataset(100, expected_centroids.view(), &mut rng);
+
+        let average_inertia = |init: InitStrategy| {
+            let mut rng = Isaac64Rng::seed_from_u64(7);
+            let n_trials = 10;
+            (0..n_trials)
+                .map(|_| {
+                    let centroids = init.initialise(20, &observations, &mut rng);
+                    let memberships = compute_cluster_memberships(&centroids, &observations);
+                    inertia(&centroids, &observations, &memberships)
+                })
+                .sum::<f64>()
+                / n_trials as f64
+        };
+
+        let parallel = average_inertia(InitStrategy::k_means_parallel());
+        let plus_plus = average_inertia(InitStrategy::KMeansPlusPlus);
+        let forgy = average_inertia(InitStrategy::Forgy);
+        assert!(parallel < 1.2 * plus_plus, "{} vs {}", parallel, plus_plus);
+        assert!(parallel < forgy, "{} vs {}", parallel, forgy);
+    }
+
+    #[test]
+    fn weighted_initialisation_follows_the_weights() {
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        let observations: Array2<f64> = Array::random_using((50, 2), StandardNormal, &mut rng);
+        // Only the first 10 observations can be picked
+        let sample_weight =
+            Array::from_shape_fn(50, |index| if index < 10 { 1. + index as f64 } else { 0. });
+
+        for init in &[
+            InitStrategy::Forgy,
+            InitStrategy::KMeansPlusPlus,
+            InitStrategy::k_means_parallel(),
+        ] {
+            let centroids =
+                init.initialise_weighted(5, &observations, sample_weight.view(), &mut rng);
+            assert!(centroids.genrows().into_iter().all(|c| {
+                count_in(&centroids, &c) == 1
+                    && count_in(&observations.slice(s![..10, ..]).to_owned(), &c) == 1
+            }));
+        }
+        // More clusters than observations with a positive weight
+        let centroids = InitStrategy::Forgy.initialise_weighted(
+            20,
+            &observations,
+            sample_weight.view(),
+            &mut rng,
+        );
+        assert!(centroids
+            .genrows()
+            .into_iter()
+            .all(|c| count_in(&centroids, &c) == 1));
+
+        // The Forgy method picks observations with a probability proportional to their weight
+        let observations = array![[0.], [1.], [2.]];
+        let sample_weight = array![1., 0., 3.];
+        let n_trials = 4000;
+        let n_heavy = (0..n_trials)
+            .filter(|_| {
+                InitStrategy::Forgy.initialise_weighted(
+                    1,
+                    &observations,
+                    sample_weight.view(),
+                    &mut rng,
+                )[[0, 0]]
+                    == 2.
+            })
+            .count();
+        assert_abs_diff_eq!(n_heavy as f64 / n_trials as f64, 0.75, epsilon = 0.03);
+    }
+}
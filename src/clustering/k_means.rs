@@ -0,0 +1,1073 @@
+use super::accelerated::{Algorithm, Bounds};
+use super::assignment::{
+    closest_centroid_with, compute_cluster_memberships_vectorised, compute_cluster_memberships_with,
+};
+use super::distance::{Distance, Euclidean};
+use super::error::KMeansError;
+use super::initialisation::{get_random_centroids, InitStrategy};
+use super::update::{update_centroids, EmptyClusterPolicy};
+use super::Float;
+use ndarray::{Array1, Array2, ArrayBase, ArrayView1, ArrayView2, Axis, Data, Ix1, Ix2};
+use ndarray_rand::rand::{Rng, SeedableRng};
+use rand_isaac::Isaac64Rng;
+use std::cmp::Ordering;
+
+/// Lloyd's algorithm: starting from `n_clusters` centroids picked with the Forgy method,
+/// alternate assignment and update steps.
+///
+/// We stop iterating if either of the following is true:
+/// - the euclidean distance between the old set of centroids and the new set of centroids
+///   is below `tolerance`;
+/// - the number of iterations has reached `max_n_iterations`.
+///
+/// Clusters left without observations are moved to the farthest observation,
+/// see [`EmptyClusterPolicy::ReseedFarthest`].
+///
+/// Returns the final centroids, a `(n_clusters, n_features)` matrix.
+/// Check out [`KMeans`] if you need more than that (e.g. cluster memberships or inertia).
+///
+/// # Panics
+///
+/// If `n_clusters` is greater than the number of observations.
+pub fn k_means<A: Float>(
+    n_clusters: usize,
+    // (n_observations, n_features)
+    observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    rng: &mut impl Rng,
+    tolerance: A,
+    max_n_iterations: usize,
+) -> Array2<A> {
+    let centroids = get_random_centroids(n_clusters, observations, rng);
+    let settings = LloydSettings {
+        tolerance,
+        max_n_iterations,
+        empty_cluster_policy: EmptyClusterPolicy::ReseedFarthest,
+        algorithm: Algorithm::Lloyd,
+    };
+    lloyd(centroids, observations, None, &settings, &Euclidean)
+        .expect("Reseeding empty clusters never fails")
+        .centroids
+}
+
+/// What happened during a single iteration of Lloyd's algorithm.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IterationRecord<A = f64> {
+    /// The euclidean distance between the centroids before and after the update step.
+    pub centroid_shift: A,
+    /// The objective after the update step, see [`inertia_with`].
+    pub inertia: A,
+}
+
+struct LloydSettings<A> {
+    tolerance: A,
+    max_n_iterations: usize,
+    empty_cluster_policy: EmptyClusterPolicy,
+    algorithm: Algorithm,
+}
+
+struct LloydOutcome<A> {
+    centroids: Array2<A>,
+    converged: bool,
+    history: Vec<IterationRecord<A>>,
+    n_skipped_distances: usize,
+}
+
+/// Alternate assignment and update steps, starting from `centroids`, until convergence
+/// or until we run out of iterations.
+///
+/// Algorithms fall back to [`Algorithm::Lloyd`] when `distance` does not support them:
+/// accelerated ones if it does not satisfy the triangle inequality,
+/// [`Algorithm::Vectorised`] if it is not euclidean.
+fn lloyd<A: Float>(
+    mut centroids: Array2<A>,
+    observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    sample_weight: Option<ArrayView1<f64>>,
+    settings: &LloydSettings<A>,
+    distance: &impl Distance,
+) -> Result<LloydOutcome<A>, KMeansError> {
+    let mut history = Vec::new();
+    let mut bounds = if distance.satisfies_triangle_inequality() {
+        Bounds::new(settings.algorithm, observations.nrows(), centroids.nrows())
+    } else {
+        None
+    };
+    loop {
+        let memberships = match &mut bounds {
+            Some(bounds) => {
+                bounds.assign(&centroids, observations, distance);
+                bounds.memberships.clone()
+            }
+            None => assign(&centroids, observations, settings.algorithm, distance),
+        };
+        let new_centroids = update_centroids(
+            &centroids,
+            observations,
+            &memberships,
+            sample_weight,
+            settings.empty_cluster_policy,
+            distance,
+        )?;
+
+        let centroid_shift = centroid_shift(&centroids, &new_centroids);
+        history.push(IterationRecord {
+            centroid_shift,
+            inertia: inertia_with(
+                &new_centroids,
+                observations,
+                &memberships,
+                sample_weight,
+                distance,
+            ),
+        });
+        let converged = centroid_shift < settings.tolerance;
+
+        if let Some(bounds) = &mut bounds {
+            bounds.shift(&centroids, &new_centroids, distance);
+        }
+        centroids = new_centroids;
+
+        if converged || history.len() >= settings.max_n_iterations {
+            return Ok(LloydOutcome {
+                centroids,
+                converged,
+                history,
+                n_skipped_distances: bounds.map_or(0, |bounds| bounds.n_skipped_distances),
+            });
+        }
+    }
+}
+
+/// The assignment step for algorithms that do not keep bounds: vectorised if `algorithm`
+/// asks for it and `distance` allows it, one observation at a time otherwise.
+fn assign<A: Float>(
+    // (n_clusters, n_features)
+    centroids: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    // (n_observations, n_features)
+    observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    algorithm: Algorithm,
+    distance: &impl Distance,
+) -> Array1<usize> {
+    if algorithm == Algorithm::Vectorised && distance.is_euclidean() {
+        compute_cluster_memberships_vectorised(centroids, observations)
+    } else {
+        compute_cluster_memberships_with(centroids, observations, distance)
+    }
+}
+
+/// The within-cluster sum of squares: the sum of the squared distances between each observation
+/// and the centroid of the cluster it belongs to.
+///
+/// This is the quantity K-means tries to minimise.
+pub fn inertia<A: Float>(
+    // (n_clusters, n_features)
+    centroids: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    // (n_observations, n_features)
+    observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    // (n_observations,)
+    cluster_memberships: &ArrayBase<impl Data<Elem = usize>, Ix1>,
+) -> A {
+    inertia_with(
+        centroids,
+        observations,
+        cluster_memberships,
+        None,
+        &Euclidean,
+    )
+}
+
+/// Generalises [`inertia`] to other distances: the sum of the [costs](Distance::cost)
+/// of each observation with respect to the centroid of the cluster it belongs to.
+///
+/// If `sample_weight` is provided, the cost of each observation is multiplied by its weight.
+pub fn inertia_with<A: Float>(
+    // (n_clusters, n_features)
+    centroids: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    // (n_observations, n_features)
+    observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    // (n_observations,)
+    cluster_memberships: &ArrayBase<impl Data<Elem = usize>, Ix1>,
+    // (n_observations,)
+    sample_weight: Option<ArrayView1<f64>>,
+    distance: &impl Distance,
+) -> A {
+    observations
+        .genrows()
+        .into_iter()
+        .zip(cluster_memberships.iter())
+        .enumerate()
+        .map(|(index, (observation, &cluster_index))| {
+            let cost =
+                distance.cost(distance.distance(&observation, &centroids.row(cluster_index)));
+            match sample_weight {
+                Some(sample_weight) => {
+                    cost * A::from_f64(sample_weight[index])
+                        .expect("Failed to convert f64 to float")
+                }
+                None => cost,
+            }
+        })
+        .sum()
+}
+
+/// The euclidean distance between two sets of centroids, seen as flat vectors.
+pub(crate) fn centroid_shift<A: Float>(old: &Array2<A>, new: &Array2<A>) -> A {
+    assert_eq!(
+        old.dim(),
+        new.dim(),
+        "Centroids should keep the same shape across iterations"
+    );
+    old.iter()
+        .zip(new.iter())
+        .map(|(&a, &b)| (a - b).powi(2))
+        .sum::<A>()
+        .sqrt()
+}
+
+/// A K-means estimator, configured with a builder-like API.
+///
+/// ```
+/// use koans::clustering::{generate_dataset, InitStrategy, KMeans};
+/// use ndarray::array;
+/// use ndarray_rand::rand::SeedableRng;
+/// use rand_isaac::Isaac64Rng;
+///
+/// let mut rng = Isaac64Rng::seed_from_u64(42);
+/// let centroids = array![[10., 10.], [1., 12.], [20., 30.], [-20., 30.]];
+/// let dataset = generate_dataset(100, centroids.view(), &mut rng);
+///
+/// let model = KMeans::new(4)
+///     .tolerance(1e-5)
+///     .max_n_iterations(200)
+///     .init(InitStrategy::Forgy)
+///     .rng(rng)
+///     .fit(&dataset)
+///     .expect("Failed to fit K-means");
+///
+/// assert_eq!(model.labels, model.predict(&dataset));
+/// ```
+pub struct KMeans<R: Rng = Isaac64Rng, D: Distance = Euclidean> {
+    hyperparameters: Hyperparameters,
+    distance: D,
+    rng: R,
+}
+
+// Kept apart from the rng: `R` is not necessarily `Sync`, while restarts might run in parallel.
+#[derive(Debug, Clone, Copy)]
+struct Hyperparameters {
+    n_clusters: usize,
+    tolerance: f64,
+    max_n_iterations: usize,
+    n_init: usize,
+    init: InitStrategy,
+    empty_cluster_policy: EmptyClusterPolicy,
+    algorithm: Algorithm,
+}
+
+impl KMeans<Isaac64Rng> {
+    /// Look for `n_clusters` clusters, using the default configuration:
+    /// - `tolerance`: 1e-4;
+    /// - `max_n_iterations`: 300;
+    /// - `n_init`: 1;
+    /// - `init`: [`InitStrategy::Forgy`];
+    /// - `empty_cluster_policy`: [`EmptyClusterPolicy::ReseedFarthest`];
+    /// - `algorithm`: [`Algorithm::Lloyd`];
+    /// - `distance`: [`Euclidean`];
+    /// - `rng`: an `Isaac64Rng` seeded from the operating system's source of randomness.
+    ///
+    /// Set `rng` to a seeded random number generator to get reproducible results.
+    pub fn new(n_clusters: usize) -> Self {
+        Self {
+            hyperparameters: Hyperparameters {
+                n_clusters,
+                tolerance: 1e-4,
+                max_n_iterations: 300,
+                n_init: 1,
+                init: InitStrategy::default(),
+                empty_cluster_policy: EmptyClusterPolicy::default(),
+                algorithm: Algorithm::default(),
+            },
+            distance: Euclidean,
+            rng: Isaac64Rng::from_entropy(),
+        }
+    }
+}
+
+impl<R: Rng, D: Distance> KMeans<R, D> {
+    /// Look for `n_clusters` clusters.
+    pub fn n_clusters(mut self, n_clusters: usize) -> Self {
+        self.hyperparameters.n_clusters = n_clusters;
+        self
+    }
+
+    /// Stop iterating when the euclidean distance between two consecutive sets
+    /// of centroids is below `tolerance`.
+    pub fn tolerance(mut self, tolerance: f64) -> Self {
+        self.hyperparameters.tolerance = tolerance;
+        self
+    }
+
+    /// Stop iterating after `max_n_iterations`, even if we have not converged.
+    pub fn max_n_iterations(mut self, max_n_iterations: usize) -> Self {
+        self.hyperparameters.max_n_iterations = max_n_iterations;
+        self
+    }
+
+    /// Run the whole algorithm `n_init` times, from different initial centroids,
+    /// and keep the run with the lowest inertia.
+    ///
+    /// With the `rayon` feature enabled, runs are executed in parallel.
+    /// The outcome does not depend on the number of threads: each run gets its own
+    /// random number generator, seeded upfront from the one passed to [`KMeans::rng`],
+    /// and ties are broken in favour of the earliest run.
+    pub fn n_init(mut self, n_init: usize) -> Self {
+        self.hyperparameters.n_init = n_init;
+        self
+    }
+
+    /// How to choose the initial set of centroids.
+    pub fn init(mut self, init: InitStrategy) -> Self {
+        self.hyperparameters.init = init;
+        self
+    }
+
+    /// What to do when a cluster is left without observations during an update step.
+    pub fn empty_cluster_policy(mut self, empty_cluster_policy: EmptyClusterPolicy) -> Self {
+        self.hyperparameters.empty_cluster_policy = empty_cluster_policy;
+        self
+    }
+
+    /// How the assignment step finds the closest centroid of each observation:
+    /// [`Algorithm::Elkan`] and [`Algorithm::Hamerly`] skip most distance computations,
+    /// see [`FittedKMeans::n_skipped_distances`].
+    ///
+    /// They rely on the triangle inequality: for distances that do not satisfy it
+    /// (see [`Distance::satisfies_triangle_inequality`]) we fall back to [`Algorithm::Lloyd`].
+    ///
+    /// [`Algorithm::Vectorised`] computes all the distances of an assignment step with a single
+    /// matrix product instead, which pays off with many features and clusters
+    /// (especially with the `blas` feature enabled). It needs a euclidean distance.
+    pub fn algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.hyperparameters.algorithm = algorithm;
+        self
+    }
+
+    /// The source of randomness used for initialisation.
+    pub fn rng<R2: Rng>(self, rng: R2) -> KMeans<R2, D> {
+        KMeans {
+            hyperparameters: self.hyperparameters,
+            distance: self.distance,
+            rng,
+        }
+    }
+
+    /// The metric used to assign observations to centroids.
+    /// It also determines how centroids are updated: see [`Distance::centre`].
+    ///
+    /// Initialisation strategies always rely on the euclidean distance.
+    pub fn distance<D2: Distance>(self, distance: D2) -> KMeans<R, D2> {
+        KMeans {
+            hyperparameters: self.hyperparameters,
+            distance,
+            rng: self.rng,
+        }
+    }
+
+    /// Cluster `observations`, a `(n_observations, n_features)` matrix.
+    ///
+    /// Fitting advances the state of the random number generator: fitting twice
+    /// in a row does not (in general) return the same model.
+    pub fn fit<A: Float>(
+        &mut self,
+        observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    ) -> Result<FittedKMeans<A, D>, KMeansError> {
+        self.fit_with(observations, None)
+    }
+
+    /// Like [`KMeans::fit`], where the i-th observation counts `sample_weight[i]` times:
+    /// handy for pre-aggregated datasets, where each row comes with a count.
+    ///
+    /// Weights are taken into account when picking the initial centroids
+    /// (see [`InitStrategy::initialise_weighted`]), when updating them and in the inertia.
+    /// Rounding aside, a weight of 2 has the same effect as duplicating the observation
+    /// (the initial centroids are sampled differently, though).
+    pub fn fit_weighted<A: Float>(
+        &mut self,
+        observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+        sample_weight: ArrayView1<f64>,
+    ) -> Result<FittedKMeans<A, D>, KMeansError> {
+        self.fit_with(observations, Some(sample_weight))
+    }
+
+    fn fit_with<A: Float>(
+        &mut self,
+        observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+        sample_weight: Option<ArrayView1<f64>>,
+    ) -> Result<FittedKMeans<A, D>, KMeansError> {
+        self.validate(observations.len_of(Axis(0)))?;
+        if let Some(sample_weight) = sample_weight {
+            validate_sample_weight(observations.len_of(Axis(0)), &sample_weight)?;
+        }
+
+        let (hyperparameters, distance) = (self.hyperparameters, self.distance);
+        let seeds: Vec<u64> = (0..hyperparameters.n_init)
+            .map(|_| self.rng.gen())
+            .collect();
+        let observations = observations.view();
+        let run = |&seed: &u64| {
+            run(
+                &hyperparameters,
+                distance,
+                &observations,
+                sample_weight,
+                seed,
+            )
+        };
+        #[cfg(feature = "rayon")]
+        let runs: Vec<Result<FittedKMeans<A, D>, KMeansError>> = {
+            use rayon::prelude::*;
+            seeds.par_iter().map(run).collect()
+        };
+        #[cfg(not(feature = "rayon"))]
+        let runs: Vec<Result<FittedKMeans<A, D>, KMeansError>> = seeds.iter().map(run).collect();
+
+        // `min_by` returns the first minimum: ties go to the earliest run
+        Ok(runs
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .min_by(|a, b| a.inertia.partial_cmp(&b.inertia).unwrap_or(Ordering::Equal))
+            .expect("`n_init` is validated to be positive"))
+    }
+
+    fn validate(&self, n_observations: usize) -> Result<(), KMeansError> {
+        let hyperparameters = &self.hyperparameters;
+        if hyperparameters.n_clusters == 0 {
+            return Err(KMeansError::ZeroClusters);
+        }
+        if hyperparameters.n_init == 0 {
+            return Err(KMeansError::ZeroInitialisations);
+        }
+        if hyperparameters.max_n_iterations == 0 {
+            return Err(KMeansError::ZeroIterations);
+        }
+        if hyperparameters.tolerance.is_nan() || hyperparameters.tolerance < 0. {
+            return Err(KMeansError::InvalidTolerance(hyperparameters.tolerance));
+        }
+        hyperparameters.init.validate()?;
+        if n_observations < hyperparameters.n_clusters {
+            return Err(KMeansError::NotEnoughObservations {
+                n_observations,
+                n_clusters: hyperparameters.n_clusters,
+            });
+        }
+        Ok(())
+    }
+}
+
+fn validate_sample_weight(
+    n_observations: usize,
+    sample_weight: &ArrayView1<f64>,
+) -> Result<(), KMeansError> {
+    if sample_weight.len() != n_observations {
+        return Err(KMeansError::SampleWeightLengthMismatch {
+            n_observations,
+            n_weights: sample_weight.len(),
+        });
+    }
+    match sample_weight
+        .iter()
+        .find(|&&weight| !(weight.is_finite() && weight > 0.))
+    {
+        Some(&weight) => Err(KMeansError::InvalidSampleWeight(weight)),
+        None => Ok(()),
+    }
+}
+
+/// A single run of the algorithm, starting from centroids picked using an `Isaac64Rng`
+/// seeded with `seed`.
+fn run<A: Float, D: Distance>(
+    hyperparameters: &Hyperparameters,
+    distance: D,
+    observations: &ArrayView2<A>,
+    sample_weight: Option<ArrayView1<f64>>,
+    seed: u64,
+) -> Result<FittedKMeans<A, D>, KMeansError> {
+    let mut rng = Isaac64Rng::seed_from_u64(seed);
+    let n_clusters = hyperparameters.n_clusters;
+    let centroids = match sample_weight {
+        Some(sample_weight) => hyperparameters.init.initialise_weighted(
+            n_clusters,
+            observations,
+            sample_weight,
+            &mut rng,
+        ),
+        None => hyperparameters
+            .init
+            .initialise(n_clusters, observations, &mut rng),
+    };
+    let LloydOutcome {
+        centroids,
+        converged,
+        history,
+        n_skipped_distances,
+    } = lloyd(
+        centroids,
+        observations,
+        sample_weight,
+        &LloydSettings {
+            tolerance: A::from_f64(hyperparameters.tolerance)
+                .expect("Failed to convert f64 to float"),
+            max_n_iterations: hyperparameters.max_n_iterations,
+            empty_cluster_policy: hyperparameters.empty_cluster_policy,
+            algorithm: hyperparameters.algorithm,
+        },
+        &distance,
+    )?;
+    let labels = assign(
+        &centroids,
+        observations,
+        hyperparameters.algorithm,
+        &distance,
+    );
+    let inertia = inertia_with(&centroids, observations, &labels, sample_weight, &distance);
+
+    Ok(FittedKMeans {
+        centroids,
+        labels,
+        inertia,
+        n_iterations: history.len(),
+        converged,
+        history,
+        n_skipped_distances,
+        distance,
+    })
+}
+
+/// The outcome of [`KMeans::fit`].
+#[derive(Debug, Clone)]
+pub struct FittedKMeans<A = f64, D = Euclidean> {
+    /// `(n_clusters, n_features)`: the i-th row is the centroid of the i-th cluster.
+    pub centroids: Array2<A>,
+    /// `(n_observations,)`: the index of the cluster each training observation belongs to.
+    pub labels: Array1<usize>,
+    /// The within-cluster sum of squares of the training observations, see [`inertia`]
+    /// (or its generalisation, [`inertia_with`], for distances other than [`Euclidean`]
+    /// and weighted observations).
+    pub inertia: A,
+    pub n_iterations: usize,
+    /// `false` if we stopped because we reached `max_n_iterations`, with centroids still moving
+    /// more than `tolerance`.
+    pub converged: bool,
+    /// One entry for each iteration, in order.
+    pub history: Vec<IterationRecord<A>>,
+    /// How many distances the assignment steps did not compute, compared to [`Algorithm::Lloyd`]
+    /// (which always computes all the observation-to-centroid ones).
+    /// The centroid-to-centroid distances used by the bounds count as computed.
+    pub n_skipped_distances: usize,
+    /// The metric used to assign observations to centroids.
+    pub distance: D,
+}
+
+impl<A: Float, D: Distance> FittedKMeans<A, D> {
+    /// The iteration history as a `(n_iterations, 2)` matrix: centroid shift in the
+    /// first column, inertia in the second one.
+    ///
+    /// Handy to plot convergence curves in the notebooks in the `python` folder:
+    ///
+    /// ```no_run
+    /// # use koans::clustering::KMeans;
+    /// # use ndarray::array;
+    /// use ndarray_npy::write_npy;
+    ///
+    /// # let dataset = array![[0., 0.], [1., 1.]];
+    /// let model = KMeans::new(2).fit(&dataset).unwrap();
+    /// write_npy("python/convergence_history.npy", model.history_array())
+    ///     .expect("Failed to write .npy file");
+    /// ```
+    pub fn history_array(&self) -> Array2<A> {
+        let mut history = Array2::zeros((self.history.len(), 2));
+        for (mut row, record) in history.genrows_mut().into_iter().zip(&self.history) {
+            row[0] = record.centroid_shift;
+            row[1] = record.inertia;
+        }
+        history
+    }
+
+    /// Assign each row of `observations`, a `(n_observations, n_features)` matrix,
+    /// to the closest centroid.
+    ///
+    /// # Panics
+    ///
+    /// If `observations` does not have the same number of features as the training data.
+    pub fn predict(&self, observations: &ArrayBase<impl Data<Elem = A>, Ix2>) -> Array1<usize> {
+        compute_cluster_memberships_with(&self.centroids, observations, &self.distance)
+    }
+
+    /// Assign a single observation to the closest centroid.
+    ///
+    /// # Panics
+    ///
+    /// If `observation` does not have the same number of features as the training data.
+    pub fn predict_one(&self, observation: &ArrayBase<impl Data<Elem = A>, Ix1>) -> usize {
+        closest_centroid_with(&self.centroids, observation, &self.distance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clustering::assignment::compute_cluster_memberships;
+    use crate::clustering::distance::{Cosine, Manhattan, SquaredEuclidean};
+    use crate::clustering::generation::generate_dataset;
+    use approx::assert_abs_diff_eq;
+    use ndarray::{array, s, stack, Array};
+    use ndarray_rand::rand_distr::Uniform;
+    use ndarray_rand::RandomExt;
+    use ndarray_stats::DeviationExt;
+
+    /// Four well separated clusters of `cluster_size` observations each,
+    /// with the centroids they were generated around.
+    fn four_blobs(cluster_size: usize) -> (Array2<f64>, Array2<f64>) {
+        let centroids = array![[10., 10.], [1., 12.], [20., 30.], [-20., 30.]];
+        let dataset = generate_dataset(
+            cluster_size,
+            centroids.view(),
+            &mut Isaac64Rng::seed_from_u64(42),
+        );
+        (centroids, dataset)
+    }
+
+    #[test]
+    fn recovers_well_separated_clusters() {
+        let (expected_centroids, dataset) = four_blobs(1000);
+        let mut rng = Isaac64Rng::seed_from_u64(7);
+
+        let centroids = k_means(4, &dataset, &mut rng, 1e-5, 200);
+
+        assert_eq!(centroids.dim(), expected_centroids.dim());
+        // Cluster indexes are an arbitrary permutation: each expected centroid
+        // should be close to one of the fitted ones.
+        for expected in expected_centroids.axis_iter(Axis(0)) {
+            assert!(centroids
+                .axis_iter(Axis(0))
+                .any(|c| c.l2_dist(&expected).unwrap() < 0.5));
+        }
+    }
+
+    #[test]
+    fn fitted_model_matches_the_function() {
+        let (_, dataset) = four_blobs(200);
+
+        let model = KMeans::new(4)
+            .tolerance(1e-5)
+            .max_n_iterations(200)
+            .rng(Isaac64Rng::seed_from_u64(7))
+            .fit(&dataset)
+            .unwrap();
+        // Each run gets its own rng, seeded from the one passed to the builder
+        let seed = Isaac64Rng::seed_from_u64(7).gen();
+        let centroids = k_means(4, &dataset, &mut Isaac64Rng::seed_from_u64(seed), 1e-5, 200);
+
+        assert_eq!(model.centroids, centroids);
+        assert_eq!(
+            model.labels,
+            compute_cluster_memberships(&centroids, &dataset)
+        );
+        assert_eq!(model.predict(&dataset), model.labels);
+        assert_eq!(model.predict_one(&dataset.row(0)), model.labels[0]);
+        assert!(model.n_iterations >= 1);
+        // Each observation is, on average, at a squared distance of ~n_features
+        // from its centroid: the clusters have unit variance.
+        assert_abs_diff_eq!(model.inertia / 800., 2., epsilon = 0.3);
+    }
+
+    #[test]
+    fn restarts_keep_the_run_with_the_lowest_inertia() {
+        let (_, dataset) = four_blobs(100);
+        let fit = |n_init: usize| {
+            KMeans::new(4)
+                .n_init(n_init)
+                .rng(Isaac64Rng::seed_from_u64(7))
+                .fit(&dataset)
+                .unwrap()
+        };
+
+        let single = fit(1);
+        let best = fit(10);
+
+        // The first restart is exactly the single run
+        assert!(best.inertia <= single.inertia);
+        // Seeds are derived upfront: same rng, same outcome
+        assert_eq!(best.centroids, fit(10).centroids);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn restarts_do_not_depend_on_the_number_of_threads() {
+        let (_, dataset) = four_blobs(100);
+        let fit_with_threads = |n_threads: usize| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(n_threads)
+                .build()
+                .unwrap();
+            pool.install(|| {
+                KMeans::new(4)
+                    .n_init(8)
+                    .rng(Isaac64Rng::seed_from_u64(7))
+                    .fit(&dataset)
+                    .unwrap()
+            })
+        };
+
+        let sequential = fit_with_threads(1);
+        let parallel = fit_with_threads(4);
+
+        assert_eq!(sequential.centroids, parallel.centroids);
+        assert_eq!(sequential.labels, parallel.labels);
+    }
+
+    #[test]
+    fn history_tracks_convergence() {
+        let (_, dataset) = four_blobs(100);
+        let fit = |max_n_iterations: usize| {
+            KMeans::new(4)
+                .tolerance(1e-5)
+                .max_n_iterations(max_n_iterations)
+                .rng(Isaac64Rng::seed_from_u64(7))
+                .fit(&dataset)
+                .unwrap()
+        };
+
+        let model = fit(200);
+        assert!(model.converged);
+        assert_eq!(model.history.len(), model.n_iterations);
+        let last = model.history.last().unwrap();
+        assert!(last.centroid_shift < 1e-5);
+        assert_abs_diff_eq!(last.inertia, model.inertia, epsilon = 1e-8);
+        // Lloyd's algorithm never increases the inertia
+        assert!(model
+            .history
+            .windows(2)
+            .all(|w| w[1].inertia <= w[0].inertia + 1e-8));
+        assert_eq!(model.history_array().dim(), (model.n_iterations, 2));
+        assert_eq!(model.history_array()[[0, 1]], model.history[0].inertia);
+
+        assert!(model.n_iterations > 1);
+        let truncated = fit(1);
+        assert!(!truncated.converged);
+        assert_eq!(truncated.n_iterations, 1);
+        assert_eq!(truncated.history[0], model.history[0]);
+    }
+
+    #[test]
+    fn empty_clusters_follow_the_policy() {
+        // Whatever observations we pick as initial centroids, they coincide:
+        // ties go to the first centroid, leaving the second one empty.
+        let observations = array![[1., 1.], [1., 1.], [1., 1.]];
+        let fit = |policy| {
+            KMeans::new(2)
+                .empty_cluster_policy(policy)
+                .rng(Isaac64Rng::seed_from_u64(42))
+                .fit(&observations)
+        };
+
+        assert_eq!(
+            fit(EmptyClusterPolicy::Error).unwrap_err(),
+            KMeansError::EmptyCluster(1)
+        );
+        for policy in &[
+            EmptyClusterPolicy::KeepPrevious,
+            EmptyClusterPolicy::ReseedFarthest,
+        ] {
+            let model = fit(*policy).unwrap();
+            assert_eq!(model.centroids, array![[1., 1.], [1., 1.]]);
+            assert_eq!(model.inertia, 0.);
+        }
+    }
+
+    #[test]
+    fn single_precision_matches_double_precision() {
+        let (_, dataset) = four_blobs(100);
+        let fit = |n_init| {
+            KMeans::new(4)
+                .n_init(n_init)
+                .rng(Isaac64Rng::seed_from_u64(7))
+        };
+
+        let double = fit(3).fit(&dataset).unwrap();
+        let single = fit(3).fit(&dataset.mapv(|x| x as f32)).unwrap();
+
+        assert_eq!(single.labels, double.labels);
+        assert_abs_diff_eq!(
+            single.centroids.mapv(f64::from),
+            double.centroids,
+            epsilon = 1e-3
+        );
+        assert_abs_diff_eq!(single.inertia as f64, double.inertia, epsilon = 1e-1);
+        let centroids: Array2<f32> = k_means(
+            4,
+            &dataset.mapv(|x| x as f32),
+            &mut Isaac64Rng::seed_from_u64(7),
+            1e-4,
+            300,
+        );
+        assert_eq!(centroids.dim(), (4, 2));
+    }
+
+    #[test]
+    fn other_distances() {
+        let (expected_centroids, dataset) = four_blobs(200);
+
+        let model = KMeans::new(4)
+            .n_init(5)
+            .distance(Manhattan)
+            .rng(Isaac64Rng::seed_from_u64(7))
+            .fit(&dataset)
+            .unwrap();
+        for expected in expected_centroids.genrows() {
+            assert!(model
+                .centroids
+                .genrows()
+                .into_iter()
+                .any(|c| Manhattan.distance(&c, &expected) < 0.5));
+        }
+        assert_eq!(model.predict(&dataset), model.labels);
+        assert_abs_diff_eq!(
+            model.inertia,
+            inertia_with(&model.centroids, &dataset, &model.labels, None, &Manhattan)
+        );
+
+        // Two bundles of directions, with very different lengths
+        let directions = array![[1., 0.1], [1., -0.1], [0.1, 1.], [-0.1, 1.]];
+        let observations =
+            stack(Axis(0), &[directions.view(), (&directions * 100.).view()]).unwrap();
+        let model = KMeans::new(2)
+            .n_init(5)
+            .distance(Cosine)
+            .rng(Isaac64Rng::seed_from_u64(7))
+            .fit(&observations)
+            .unwrap();
+        assert_eq!(model.labels[0], model.labels[1]);
+        assert_ne!(model.labels[0], model.labels[2]);
+        assert_eq!(model.labels.slice(s![..4]), model.labels.slice(s![4..]));
+        for centroid in model.centroids.genrows() {
+            assert_abs_diff_eq!(centroid.dot(&centroid), 1., epsilon = 1e-12);
+        }
+    }
+
+    #[test]
+    fn accelerated_algorithms_match_lloyd() {
+        let (_, dataset) = four_blobs(250);
+        let fit = |n_clusters: usize, algorithm: Algorithm| {
+            KMeans::new(n_clusters)
+                .algorithm(algorithm)
+                .tolerance(1e-8)
+                .rng(Isaac64Rng::seed_from_u64(7))
+                .fit(&dataset)
+                .unwrap()
+        };
+
+        for &n_clusters in &[4, 7] {
+            let lloyd = fit(n_clusters, Algorithm::Lloyd);
+            assert_eq!(lloyd.n_skipped_distances, 0);
+            for &algorithm in &[Algorithm::Elkan, Algorithm::Hamerly] {
+                let accelerated = fit(n_clusters, algorithm);
+                assert_eq!(accelerated.labels, lloyd.labels);
+                assert_eq!(accelerated.centroids, lloyd.centroids);
+                assert_eq!(accelerated.history, lloyd.history);
+
+                let n_distances = dataset.nrows() * n_clusters * accelerated.n_iterations;
+                assert!(
+                    accelerated.n_skipped_distances > n_distances / 2,
+                    "{:?} skipped {} out of {} distances",
+                    algorithm,
+                    accelerated.n_skipped_distances,
+                    n_distances
+                );
+            }
+        }
+
+        // The cosine distance does not satisfy the triangle inequality: no bound is kept
+        let model = KMeans::new(4)
+            .algorithm(Algorithm::Elkan)
+            .distance(Cosine)
+            .rng(Isaac64Rng::seed_from_u64(7))
+            .fit(&dataset)
+            .unwrap();
+        assert_eq!(model.n_skipped_distances, 0);
+    }
+
+    #[test]
+    fn vectorised_algorithm_matches_lloyd() {
+        let (_, dataset) = four_blobs(250);
+        let fit = |n_clusters: usize, algorithm: Algorithm| {
+            KMeans::new(n_clusters)
+                .algorithm(algorithm)
+                .tolerance(1e-8)
+                .rng(Isaac64Rng::seed_from_u64(7))
+                .fit(&dataset)
+                .unwrap()
+        };
+        for &n_clusters in &[4, 7] {
+            let lloyd = fit(n_clusters, Algorithm::Lloyd);
+            let vectorised = fit(n_clusters, Algorithm::Vectorised);
+            assert_eq!(vectorised.labels, lloyd.labels);
+            assert_eq!(vectorised.centroids, lloyd.centroids);
+            assert_eq!(vectorised.history, lloyd.history);
+            assert_eq!(vectorised.n_skipped_distances, 0);
+        }
+
+        // Only euclidean distances take the vectorised path
+        let centroids = dataset.slice(s![..7, ..]);
+        let vectorised = compute_cluster_memberships_vectorised(&centroids, &dataset);
+        assert_eq!(
+            assign(&centroids, &dataset, Algorithm::Vectorised, &Euclidean),
+            vectorised
+        );
+        assert_eq!(
+            assign(
+                &centroids,
+                &dataset,
+                Algorithm::Vectorised,
+                &SquaredEuclidean
+            ),
+            vectorised
+        );
+        assert_eq!(
+            assign(&centroids, &dataset, Algorithm::Vectorised, &Manhattan),
+            compute_cluster_memberships_with(&centroids, &dataset, &Manhattan)
+        );
+
+        // Other distances fall back to Lloyd
+        let fit = |algorithm: Algorithm| {
+            KMeans::new(4)
+                .algorithm(algorithm)
+                .distance(Manhattan)
+                .rng(Isaac64Rng::seed_from_u64(7))
+                .fit(&dataset)
+                .unwrap()
+        };
+        let (lloyd, vectorised) = (fit(Algorithm::Lloyd), fit(Algorithm::Vectorised));
+        assert_eq!(vectorised.labels, lloyd.labels);
+        assert_eq!(vectorised.centroids, lloyd.centroids);
+    }
+
+    #[test]
+    fn invalid_parameters_are_rejected() {
+        let observations = array![[0., 0.], [1., 1.]];
+
+        assert_eq!(
+            KMeans::new(0).fit(&observations).unwrap_err(),
+            KMeansError::ZeroClusters
+        );
+        assert_eq!(
+            KMeans::new(3).fit(&observations).unwrap_err(),
+            KMeansError::NotEnoughObservations {
+                n_observations: 2,
+                n_clusters: 3
+            }
+        );
+        assert_eq!(
+            KMeans::new(1).n_init(0).fit(&observations).unwrap_err(),
+            KMeansError::ZeroInitialisations
+        );
+        assert_eq!(
+            KMeans::new(1)
+                .max_n_iterations(0)
+                .fit(&observations)
+                .unwrap_err(),
+            KMeansError::ZeroIterations
+        );
+        assert!(KMeans::new(1)
+            .tolerance(f64::NAN)
+            .fit(&observations)
+            .is_err());
+        assert_eq!(
+            KMeans::new(1)
+                .fit_weighted(&observations, array![1.].view())
+                .unwrap_err(),
+            KMeansError::SampleWeightLengthMismatch {
+                n_observations: 2,
+                n_weights: 1
+            }
+        );
+        assert_eq!(
+            KMeans::new(1)
+                .fit_weighted(&observations, array![1., -1.].view())
+                .unwrap_err(),
+            KMeansError::InvalidSampleWeight(-1.)
+        );
+        assert!(KMeans::new(1)
+            .fit_weighted(&observations, array![0., f64::INFINITY].view())
+            .is_err());
+        let k_means_parallel = |n_rounds, oversampling_factor| {
+            KMeans::new(1).init(InitStrategy::KMeansParallel {
+                n_rounds,
+                oversampling_factor,
+            })
+        };
+        assert_eq!(
+            k_means_parallel(0, 2.).fit(&observations).unwrap_err(),
+            KMeansError::ZeroRounds
+        );
+        assert_eq!(
+            k_means_parallel(5, 0.).fit(&observations).unwrap_err(),
+            KMeansError::InvalidOversamplingFactor(0.)
+        );
+        assert!(k_means_parallel(5, f64::NAN).fit(&observations).is_err());
+    }
+
+    #[test]
+    fn weights_are_equivalent_to_duplicated_observations() {
+        let (expected_centroids, dataset) = four_blobs(100);
+        let mut rng = Isaac64Rng::seed_from_u64(7);
+        let counts: Array1<usize> = Array::random_using(400, Uniform::new(1, 5), &mut rng);
+        let sample_weight = counts.mapv(|count| count as f64);
+        let duplicated_indices: Vec<usize> = counts
+            .iter()
+            .enumerate()
+            .flat_map(|(index, &count)| vec![index; count])
+            .collect();
+        let duplicated_dataset = dataset.select(Axis(0), &duplicated_indices);
+
+        let labels = compute_cluster_memberships(&expected_centroids, &dataset);
+        assert_abs_diff_eq!(
+            inertia_with(
+                &expected_centroids,
+                &dataset,
+                &labels,
+                Some(sample_weight.view()),
+                &Euclidean
+            ),
+            inertia(
+                &expected_centroids,
+                &duplicated_dataset,
+                &labels.select(Axis(0), &duplicated_indices)
+            ),
+            epsilon = 1e-6
+        );
+
+        let mut k_means = KMeans::new(4)
+            .n_init(5)
+            .init(InitStrategy::KMeansPlusPlus)
+            .tolerance(1e-10)
+            .rng(Isaac64Rng::seed_from_u64(7));
+        let weighted = k_means
+            .fit_weighted(&dataset, sample_weight.view())
+            .unwrap();
+        let duplicated = k_means.fit(&duplicated_dataset).unwrap();
+
+        // Same clusters, possibly in a different order
+        for centroid in weighted.centroids.genrows() {
+            let closest = duplicated.predict_one(&centroid);
+            assert_abs_diff_eq!(centroid, duplicated.centroids.row(closest), epsilon = 1e-10);
+        }
+        assert_abs_diff_eq!(weighted.inertia, duplicated.inertia, epsilon = 1e-6);
+        assert_eq!(
+            weighted.labels.select(Axis(0), &duplicated_indices),
+            duplicated
+                .labels
+                .mapv(|label| weighted.predict_one(&duplicated.centroids.row(label)))
+        );
+    }
+}
@@ -0,0 +1,597 @@
+//! How good is a clustering?
+//!
+//! Internal metrics only look at the observations and the labels assigned to them:
+//! - [`silhouette_score`];
+//! - [`davies_bouldin_score`];
+//! - [`calinski_harabasz_score`].
+//!
+//! External metrics compare the labels with the ground truth, e.g. the cluster each observation
+//! was generated from by [`generate_dataset`](super::generate_dataset). They do not depend on the
+//! actual values of the labels: a clustering that matches the ground truth up to a permutation
+//! of the labels gets a perfect score.
+//! - [`contingency_matrix`];
+//! - [`adjusted_rand_score`];
+//! - [`normalized_mutual_info_score`];
+//! - [`homogeneity_completeness_v_measure`].
+//!
+//! Labels are indices, as returned by [`compute_cluster_memberships`](super::compute_cluster_memberships):
+//! they do not need to be contiguous, but they should be small, since some metrics allocate
+//! one entry for each label up to the largest one.
+use super::distance::{Distance, Euclidean};
+use super::update::compute_centroids_hashmap;
+use super::Float;
+use ndarray::{Array1, Array2, ArrayBase, Axis, Data, Ix1, Ix2};
+
+/// The silhouette coefficient of each observation (Rousseeuw, 1987), using the euclidean distance:
+/// see [`silhouette_samples_with`].
+pub fn silhouette_samples<A: Float>(
+    // (n_observations, n_features)
+    observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    // (n_observations,)
+    labels: &ArrayBase<impl Data<Elem = usize>, Ix1>,
+) -> Array1<A> {
+    silhouette_samples_with(observations, labels, &Euclidean)
+}
+
+/// The silhouette coefficient of each observation, measuring distances with `distance`:
+///
+/// ```text
+/// s = (b - a) / max(a, b)
+/// ```
+///
+/// where `a` is the mean distance of the observation from the other members of its cluster
+/// and `b` is its mean distance from the members of the closest other cluster.
+///
+/// It ranges from -1 (the observation would be better off in another cluster)
+/// to 1 (the observation sits well within its cluster, far from the others).
+/// It is 0 for observations alone in their cluster, as well as when there is a single cluster.
+///
+/// It needs the distance between every pair of observations: `O(n_observations²)`.
+///
+/// # Panics
+///
+/// If `labels` does not have one label for each observation.
+pub fn silhouette_samples_with<A: Float>(
+    // (n_observations, n_features)
+    observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    // (n_observations,)
+    labels: &ArrayBase<impl Data<Elem = usize>, Ix1>,
+    distance: &impl Distance,
+) -> Array1<A> {
+    assert_eq!(
+        observations.nrows(),
+        labels.len(),
+        "There must be one label for each observation"
+    );
+    let n_clusters = labels.iter().max().map_or(0, |&label| label + 1);
+    let mut cluster_sizes = vec![0; n_clusters];
+    for &label in labels {
+        cluster_sizes[label] += 1;
+    }
+
+    let mut silhouettes = Array1::zeros(observations.nrows());
+    for (index, observation) in observations.genrows().into_iter().enumerate() {
+        let own_cluster = labels[index];
+        if cluster_sizes[own_cluster] <= 1 {
+            continue;
+        }
+        // The sum of the distances from the members of each cluster
+        let mut total_distances = vec![A::zero(); n_clusters];
+        for (other, &label) in observations.genrows().into_iter().zip(labels) {
+            total_distances[label] += distance.distance(&observation, &other);
+        }
+        let mean_distance = |label: usize, n_others: usize| {
+            total_distances[label]
+                / A::from_usize(n_others).expect("Failed to convert usize to float")
+        };
+
+        let a = mean_distance(own_cluster, cluster_sizes[own_cluster] - 1);
+        let b = (0..n_clusters)
+            .filter(|&label| label != own_cluster && cluster_sizes[label] > 0)
+            .map(|label| mean_distance(label, cluster_sizes[label]))
+            .fold(A::infinity(), A::min);
+        if b.is_finite() && a.max(b) > A::zero() {
+            silhouettes[index] = (b - a) / a.max(b);
+        }
+    }
+    silhouettes
+}
+
+/// The mean silhouette coefficient of all observations, using the euclidean distance:
+/// see [`silhouette_score_with`].
+pub fn silhouette_score<A: Float>(
+    // (n_observations, n_features)
+    observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    // (n_observations,)
+    labels: &ArrayBase<impl Data<Elem = usize>, Ix1>,
+) -> Option<A> {
+    silhouette_score_with(observations, labels, &Euclidean)
+}
+
+/// The mean [silhouette coefficient](silhouette_samples_with) of all observations:
+/// the higher, the better separated the clusters.
+///
+/// Returns `None` if there are fewer than two distinct labels: the silhouette is not defined.
+///
+/// # Panics
+///
+/// If `labels` does not have one label for each observation.
+pub fn silhouette_score_with<A: Float>(
+    // (n_observations, n_features)
+    observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    // (n_observations,)
+    labels: &ArrayBase<impl Data<Elem = usize>, Ix1>,
+    distance: &impl Distance,
+) -> Option<A> {
+    let first_label = labels.iter().next()?;
+    if labels.iter().all(|label| label == first_label) {
+        return None;
+    }
+    silhouette_samples_with(observations, labels, distance).mean()
+}
+
+/// The Davies–Bouldin index (Davies and Bouldin, 1979), using the euclidean distance:
+/// the average, over all clusters, of the similarity with the most similar other cluster.
+///
+/// The similarity of two clusters is the sum of their spreads (the mean distance of their members
+/// from their centroid) divided by the distance between their centroids.
+/// The lower, the better separated the clusters: 0 is the best possible score.
+///
+/// Returns `None` if there are fewer than two distinct labels.
+///
+/// # Panics
+///
+/// If `labels` does not have one label for each observation.
+pub fn davies_bouldin_score<A: Float>(
+    // (n_observations, n_features)
+    observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    // (n_observations,)
+    labels: &ArrayBase<impl Data<Elem = usize>, Ix1>,
+) -> Option<A> {
+    assert_eq!(
+        observations.nrows(),
+        labels.len(),
+        "There must be one label for each observation"
+    );
+    let clusters: Vec<(usize, Array1<A>)> = {
+        let mut clusters: Vec<_> = compute_centroids_hashmap(observations, labels)
+            .into_iter()
+            .map(|(label, statistics)| (label, statistics.mean))
+            .collect();
+        clusters.sort_by_key(|(label, _)| *label);
+        clusters
+    };
+    if clusters.len() < 2 {
+        return None;
+    }
+    let max_label = clusters.last().map_or(0, |(label, _)| *label);
+    let mut centroids = vec![None; max_label + 1];
+    for (label, centroid) in &clusters {
+        centroids[*label] = Some(centroid);
+    }
+
+    // The mean distance of the members of each cluster from its centroid
+    let mut spreads = vec![A::zero(); max_label + 1];
+    let mut sizes = vec![0; max_label + 1];
+    for (observation, &label) in observations.genrows().into_iter().zip(labels) {
+        let centroid = centroids[label].expect("Each label has a centroid");
+        spreads[label] += Euclidean.distance(&observation, centroid);
+        sizes[label] += 1;
+    }
+    for (spread, &size) in spreads.iter_mut().zip(&sizes) {
+        if size > 0 {
+            *spread /= A::from_usize(size).expect("Failed to convert usize to float");
+        }
+    }
+
+    let total_similarity = clusters
+        .iter()
+        .map(|(label, centroid)| {
+            clusters
+                .iter()
+                .filter(|(other_label, _)| other_label != label)
+                .map(|(other_label, other_centroid)| {
+                    let separation = Euclidean.distance(centroid, other_centroid);
+                    // Coinciding centroids: as in scikit-learn, they do not count as similar
+                    if separation > A::zero() {
+                        (spreads[*label] + spreads[*other_label]) / separation
+                    } else {
+                        A::zero()
+                    }
+                })
+                .fold(A::zero(), A::max)
+        })
+        .sum::<A>();
+    Some(
+        total_similarity / A::from_usize(clusters.len()).expect("Failed to convert usize to float"),
+    )
+}
+
+/// The Calinski–Harabasz index (Calinski and Harabasz, 1974), also known as the variance ratio
+/// criterion: the ratio between the dispersion of the centroids around the global mean and
+/// the dispersion of the observations around their centroids, each one divided by its
+/// number of degrees of freedom.
+///
+/// ```text
+/// ((between-cluster sum of squares) / (k - 1)) / ((within-cluster sum of squares) / (n - k))
+/// ```
+///
+/// The higher, the denser and better separated the clusters.
+///
+/// Returns `None` unless there are at least two distinct labels and fewer distinct labels than
+/// observations.
+///
+/// # Panics
+///
+/// If `labels` does not have one label for each observation.
+pub fn calinski_harabasz_score<A: Float>(
+    // (n_observations, n_features)
+    observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    // (n_observations,)
+    labels: &ArrayBase<impl Data<Elem = usize>, Ix1>,
+) -> Option<A> {
+    assert_eq!(
+        observations.nrows(),
+        labels.len(),
+        "There must be one label for each observation"
+    );
+    let n_observations = observations.nrows();
+    let clusters = compute_centroids_hashmap(observations, labels);
+    let n_clusters = clusters.len();
+    if n_clusters < 2 || n_clusters >= n_observations {
+        return None;
+    }
+    let mean = observations.mean_axis(Axis(0))?;
+
+    let squared_distance = |a: &Array1<A>, b: &Array1<A>| {
+        a.iter()
+            .zip(b.iter())
+            .map(|(&a, &b)| (a - b).powi(2))
+            .sum::<A>()
+    };
+    let between_clusters = clusters
+        .values()
+        .map(|statistics| {
+            squared_distance(&statistics.mean, &mean)
+                * A::from_usize(statistics.n_observations)
+                    .expect("Failed to convert usize to float")
+        })
+        .sum::<A>();
+    let within_clusters = observations
+        .genrows()
+        .into_iter()
+        .zip(labels)
+        .map(|(observation, label)| {
+            squared_distance(&observation.to_owned(), &clusters[label].mean)
+        })
+        .sum::<A>();
+    if within_clusters == A::zero() {
+        // As in scikit-learn
+        return Some(A::one());
+    }
+
+    let to_float = |x: usize| A::from_usize(x).expect("Failed to convert usize to float");
+    Some(
+        (between_clusters / to_float(n_clusters - 1))
+            / (within_clusters / to_float(n_observations - n_clusters)),
+    )
+}
+
+/// How many observations have each pair of labels: the entry `(i, j)` counts the observations
+/// with `labels_true` equal to `i` and `labels_pred` equal to `j`.
+///
+/// The output has shape `(max(labels_true) + 1, max(labels_pred) + 1)`.
+///
+/// # Panics
+///
+/// If `labels_true` and `labels_pred` have different lengths.
+pub fn contingency_matrix(
+    labels_true: &ArrayBase<impl Data<Elem = usize>, Ix1>,
+    labels_pred: &ArrayBase<impl Data<Elem = usize>, Ix1>,
+) -> Array2<usize> {
+    assert_eq!(
+        labels_true.len(),
+        labels_pred.len(),
+        "The two labellings must have the same length"
+    );
+    let n_true_labels = labels_true.iter().max().map_or(0, |&label| label + 1);
+    let n_pred_labels = labels_pred.iter().max().map_or(0, |&label| label + 1);
+    let mut contingency = Array2::zeros((n_true_labels, n_pred_labels));
+    for (&true_label, &pred_label) in labels_true.iter().zip(labels_pred) {
+        contingency[[true_label, pred_label]] += 1;
+    }
+    contingency
+}
+
+/// The Rand index, adjusted for chance (Hubert and Arabie, 1985).
+///
+/// The Rand index is the fraction of pairs of observations the two labellings agree on
+/// (either in the same cluster in both, or in different clusters in both).
+/// Adjusting it for chance, a random labelling scores close to 0 (possibly below it)
+/// while a perfect match, up to a permutation of the labels, scores 1.
+///
+/// # Panics
+///
+/// If `labels_true` and `labels_pred` have different lengths.
+pub fn adjusted_rand_score(
+    labels_true: &ArrayBase<impl Data<Elem = usize>, Ix1>,
+    labels_pred: &ArrayBase<impl Data<Elem = usize>, Ix1>,
+) -> f64 {
+    let contingency = contingency_matrix(labels_true, labels_pred);
+    let pairs = |n: usize| (n * n.saturating_sub(1)) as f64 / 2.;
+    let sum_of_pairs = |counts: Array1<usize>| counts.iter().map(|&n| pairs(n)).sum::<f64>();
+
+    let index: f64 = contingency.iter().map(|&n| pairs(n)).sum();
+    let true_pairs = sum_of_pairs(contingency.sum_axis(Axis(1)));
+    let pred_pairs = sum_of_pairs(contingency.sum_axis(Axis(0)));
+    let expected_index = true_pairs * pred_pairs / pairs(labels_true.len()).max(1.);
+    let max_index = (true_pairs + pred_pairs) / 2.;
+    if max_index == expected_index {
+        // A single cluster (or only singletons) in both labellings: a perfect match
+        return 1.;
+    }
+    (index - expected_index) / (max_index - expected_index)
+}
+
+/// The mutual information between the two labellings, normalised by the arithmetic mean
+/// of their entropies: from 0 (independent labellings) to 1 (a perfect match, up to
+/// a permutation of the labels).
+///
+/// # Panics
+///
+/// If `labels_true` and `labels_pred` have different lengths.
+pub fn normalized_mutual_info_score(
+    labels_true: &ArrayBase<impl Data<Elem = usize>, Ix1>,
+    labels_pred: &ArrayBase<impl Data<Elem = usize>, Ix1>,
+) -> f64 {
+    let entropies = Entropies::new(&contingency_matrix(labels_true, labels_pred));
+    let normaliser = (entropies.true_entropy + entropies.pred_entropy) / 2.;
+    if normaliser == 0. {
+        // A single cluster in both labellings
+        return 1.;
+    }
+    entropies.mutual_information / normaliser
+}
+
+/// Homogeneity (each cluster only contains members of a single class), completeness
+/// (all members of a class are assigned to the same cluster) and their harmonic mean,
+/// the V-measure (Rosenberg and Hirschberg, 2007).
+///
+/// All of them range from 0 to 1, the higher the better.
+///
+/// # Panics
+///
+/// If `labels_true` and `labels_pred` have different lengths.
+pub fn homogeneity_completeness_v_measure(
+    labels_true: &ArrayBase<impl Data<Elem = usize>, Ix1>,
+    labels_pred: &ArrayBase<impl Data<Elem = usize>, Ix1>,
+) -> (f64, f64, f64) {
+    let entropies = Entropies::new(&contingency_matrix(labels_true, labels_pred));
+    let ratio = |entropy: f64| {
+        if entropy == 0. {
+            1.
+        } else {
+            entropies.mutual_information / entropy
+        }
+    };
+    let homogeneity = ratio(entropies.true_entropy);
+    let completeness = ratio(entropies.pred_entropy);
+    let v_measure = if homogeneity + completeness == 0. {
+        0.
+    } else {
+        2. * homogeneity * completeness / (homogeneity + completeness)
+    };
+    (homogeneity, completeness, v_measure)
+}
+
+/// The V-measure: see [`homogeneity_completeness_v_measure`].
+pub fn v_measure_score(
+    labels_true: &ArrayBase<impl Data<Elem = usize>, Ix1>,
+    labels_pred: &ArrayBase<impl Data<Elem = usize>, Ix1>,
+) -> f64 {
+    homogeneity_completeness_v_measure(labels_true, labels_pred).2
+}
+
+/// The entropies of two labellings (in nats) and their mutual information,
+/// from their contingency matrix.
+struct Entropies {
+    true_entropy: f64,
+    pred_entropy: f64,
+    mutual_information: f64,
+}
+
+impl Entropies {
+    fn new(contingency: &Array2<usize>) -> Self {
+        let n_observations = contingency.sum() as f64;
+        let entropy = |counts: Array1<usize>| {
+            -counts
+                .iter()
+                .filter(|&&count| count > 0)
+                .map(|&count| {
+                    let p = count as f64 / n_observations;
+                    p * p.ln()
+                })
+                .sum::<f64>()
+        };
+        let true_counts = contingency.sum_axis(Axis(1));
+        let pred_counts = contingency.sum_axis(Axis(0));
+        let mutual_information = contingency
+            .indexed_iter()
+            .filter(|(_, &count)| count > 0)
+            .map(|((i, j), &count)| {
+                let count = count as f64;
+                let expected = true_counts[i] as f64 * pred_counts[j] as f64 / n_observations;
+                count / n_observations * (count / expected).ln()
+            })
+            .sum::<f64>()
+            // Rounding might push it slightly below 0
+            .max(0.);
+        Self {
+            true_entropy: entropy(true_counts),
+            pred_entropy: entropy(pred_counts),
+            mutual_information,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clustering::distance::Manhattan;
+    use approx::assert_abs_diff_eq;
+    use ndarray::array;
+
+    #[test]
+    fn silhouette_of_two_clusters() {
+        let observations = array![[0.], [1.], [4.], [5.]];
+        let labels = array![0, 0, 1, 1];
+
+        let silhouettes = silhouette_samples(&observations, &labels);
+
+        let (outer, inner) = ((4.5 - 1.) / 4.5, (3.5 - 1.) / 3.5);
+        assert_abs_diff_eq!(
+            silhouettes,
+            array![outer, inner, inner, outer],
+            epsilon = 1e-12
+        );
+        assert_abs_diff_eq!(
+            silhouette_score(&observations, &labels).unwrap(),
+            (outer + inner) / 2.,
+            epsilon = 1e-12
+        );
+        // A poor split scores lower
+        let labels = array![0, 1, 0, 1];
+        assert!(silhouette_score(&observations, &labels).unwrap() < 0.);
+    }
+
+    #[test]
+    fn silhouette_edge_cases() {
+        let observations = array![[0., 0.], [1., 1.], [5., 5.]];
+
+        // A single cluster
+        assert_eq!(silhouette_score(&observations, &array![2, 2, 2]), None);
+        assert_eq!(
+            silhouette_samples(&observations, &array![0, 0, 0]),
+            array![0., 0., 0.]
+        );
+        // Singletons score 0
+        let silhouettes = silhouette_samples(&observations, &array![0, 0, 1]);
+        assert_eq!(silhouettes[2], 0.);
+        // Duplicated observations
+        let duplicates = array![[1., 1.], [1., 1.], [1., 1.], [1., 1.]];
+        assert_eq!(silhouette_score(&duplicates, &array![0, 0, 1, 1]), Some(0.));
+        // Labels do not need to be contiguous
+        assert_abs_diff_eq!(
+            silhouette_score_with(&observations, &array![0, 0, 3], &Manhattan).unwrap(),
+            silhouette_score_with(&observations, &array![0, 0, 1], &Manhattan).unwrap()
+        );
+    }
+
+    #[test]
+    fn davies_bouldin_and_calinski_harabasz() {
+        let observations = array![[0.], [2.], [10.], [12.]];
+        let labels = array![0, 0, 1, 1];
+
+        // Both clusters have a spread of 1, their centroids are 10 apart
+        assert_abs_diff_eq!(
+            davies_bouldin_score(&observations, &labels).unwrap(),
+            0.2,
+            epsilon = 1e-12
+        );
+        // Between-cluster sum of squares: 100, within-cluster: 4
+        assert_abs_diff_eq!(
+            calinski_harabasz_score(&observations, &labels).unwrap(),
+            (100. / 1.) / (4. / 2.),
+            epsilon = 1e-12
+        );
+        // Worse splits score worse
+        let labels = array![0, 1, 0, 1];
+        assert!(davies_bouldin_score(&observations, &labels).unwrap() > 0.2);
+        assert!(calinski_harabasz_score(&observations, &labels).unwrap() < 50.);
+
+        assert_eq!(
+            davies_bouldin_score(&observations, &array![1, 1, 1, 1]),
+            None
+        );
+        assert_eq!(
+            calinski_harabasz_score(&observations, &array![1, 1, 1, 1]),
+            None
+        );
+        assert_eq!(
+            calinski_harabasz_score(&observations, &array![0, 1, 2, 3]),
+            None
+        );
+    }
+
+    #[test]
+    fn external_metrics() {
+        let labels_true = array![0, 0, 1, 1];
+        let labels_pred = array![0, 0, 1, 2];
+
+        assert_eq!(
+            contingency_matrix(&labels_true, &labels_pred),
+            array![[2, 0, 0], [0, 1, 1]]
+        );
+        // Same values as scikit-learn
+        assert_abs_diff_eq!(
+            adjusted_rand_score(&labels_true, &labels_pred),
+            0.571_428_571_428_571_5,
+            epsilon = 1e-12
+        );
+        assert_abs_diff_eq!(
+            normalized_mutual_info_score(&labels_true, &labels_pred),
+            0.8,
+            epsilon = 1e-12
+        );
+        // Every cluster is pure, but class 1 is split in two
+        let (homogeneity, completeness, v_measure) =
+            homogeneity_completeness_v_measure(&labels_true, &labels_pred);
+        assert_abs_diff_eq!(homogeneity, 1., epsilon = 1e-12);
+        assert_abs_diff_eq!(completeness, 2. / 3., epsilon = 1e-12);
+        assert_abs_diff_eq!(v_measure, 0.8, epsilon = 1e-12);
+
+        // A permutation of the labels is a perfect match
+        let permuted = array![5, 5, 2, 2];
+        assert_abs_diff_eq!(adjusted_rand_score(&labels_true, &permuted), 1.);
+        assert_abs_diff_eq!(normalized_mutual_info_score(&labels_true, &permuted), 1.);
+        assert_abs_diff_eq!(v_measure_score(&labels_true, &permuted), 1.);
+        // A labelling independent of the ground truth
+        let independent = array![0, 1, 0, 1];
+        assert!(adjusted_rand_score(&labels_true, &independent) < 0.);
+        assert_abs_diff_eq!(
+            normalized_mutual_info_score(&labels_true, &independent),
+            0.,
+            epsilon = 1e-12
+        );
+        // Degenerate labellings
+        assert_eq!(adjusted_rand_score(&array![0, 0], &array![1, 1]), 1.);
+        assert_eq!(
+            normalized_mutual_info_score(&array![0, 0], &array![1, 1]),
+            1.
+        );
+    }
+
+    #[test]
+    fn k_means_recovers_the_ground_truth() {
+        use crate::clustering::{compute_cluster_memberships, generate_labelled_dataset, k_means};
+        use ndarray_rand::rand::SeedableRng;
+        use rand_isaac::Isaac64Rng;
+
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        let centroids = array![[10., 10.], [-10., 10.], [0., -10.]];
+        let (observations, labels_true) =
+            generate_labelled_dataset(100, centroids.view(), true, &mut rng);
+
+        let fitted_centroids = k_means(3, &observations, &mut rng, 1e-5, 100);
+        let labels_pred = compute_cluster_memberships(&fitted_centroids, &observations);
+
+        assert_abs_diff_eq!(adjusted_rand_score(&labels_true, &labels_pred), 1.);
+        assert_abs_diff_eq!(
+            normalized_mutual_info_score(&labels_true, &labels_pred),
+            1.,
+            epsilon = 1e-12
+        );
+        // The ground truth is a pretty good clustering too
+        assert!(silhouette_score(&observations, &labels_true).unwrap() > 0.8);
+    }
+}
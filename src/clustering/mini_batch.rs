@@ -0,0 +1,345 @@
+use super::assignment::compute_cluster_memberships;
+use super::distance::Euclidean;
+use super::error::KMeansError;
+use super::initialisation::{sample_indices, InitStrategy};
+use super::k_means::{centroid_shift, inertia, FittedKMeans, IterationRecord};
+use super::update::IncrementalMean;
+use super::Float;
+use ndarray::{stack, Array1, Array2, ArrayBase, Axis, Data, Ix2};
+use ndarray_rand::rand::{Rng, SeedableRng};
+use rand_isaac::Isaac64Rng;
+
+/// Mini-batch K-means (Sculley, 2010): each step assigns a small batch of observations
+/// to their closest centroids and nudges each centroid towards the observations assigned to it.
+///
+/// Each centroid is an [`IncrementalMean`] of all the observations it has been assigned so far,
+/// [seeded](IncrementalMean::seeded) with its initial position (which carries no weight):
+/// the learning rate of each centroid is the inverse of the number of observations it has seen,
+/// decreasing as it settles down.
+///
+/// Use [`MiniBatchKMeans::fit`] if your dataset fits in memory, [`MiniBatchKMeans::partial_fit`]
+/// to feed it one chunk at a time (e.g. reading it from disk).
+///
+/// ```
+/// use koans::clustering::{generate_dataset, MiniBatchKMeans};
+/// use ndarray::{array, Axis};
+/// use ndarray_rand::rand::SeedableRng;
+/// use rand_isaac::Isaac64Rng;
+///
+/// let mut rng = Isaac64Rng::seed_from_u64(42);
+/// let dataset = generate_dataset(500, array![[0., 0.], [50., 50.]].view(), &mut rng);
+///
+/// let mut model = MiniBatchKMeans::new(2).batch_size(100).rng(rng);
+/// for chunk in dataset.axis_chunks_iter(Axis(0), 250) {
+///     model.partial_fit(&chunk).expect("Failed to update the model");
+/// }
+/// assert_eq!(model.centroids().unwrap().dim(), (2, 2));
+/// ```
+pub struct MiniBatchKMeans<A = f64, R: Rng = Isaac64Rng> {
+    n_clusters: usize,
+    batch_size: usize,
+    tolerance: f64,
+    max_n_iterations: usize,
+    init: InitStrategy,
+    rng: R,
+    centroids: Option<Vec<IncrementalMean<A>>>,
+}
+
+impl<A: Float> MiniBatchKMeans<A, Isaac64Rng> {
+    /// Look for `n_clusters` clusters, using the default configuration:
+    /// - `batch_size`: 1024;
+    /// - `tolerance`: 1e-4;
+    /// - `max_n_iterations`: 100;
+    /// - `init`: [`InitStrategy::KMeansPlusPlus`];
+    /// - `rng`: an `Isaac64Rng` seeded from the operating system's source of randomness.
+    pub fn new(n_clusters: usize) -> Self {
+        Self {
+            n_clusters,
+            batch_size: 1024,
+            tolerance: 1e-4,
+            max_n_iterations: 100,
+            init: InitStrategy::KMeansPlusPlus,
+            rng: Isaac64Rng::from_entropy(),
+            centroids: None,
+        }
+    }
+}
+
+impl<A: Float, R: Rng> MiniBatchKMeans<A, R> {
+    /// The number of observations sampled at each step of [`MiniBatchKMeans::fit`].
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// [`MiniBatchKMeans::fit`] stops when the euclidean distance between the centroids
+    /// before and after a step is below `tolerance`.
+    pub fn tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// [`MiniBatchKMeans::fit`] stops after `max_n_iterations` steps, even if it has not converged.
+    pub fn max_n_iterations(mut self, max_n_iterations: usize) -> Self {
+        self.max_n_iterations = max_n_iterations;
+        self
+    }
+
+    /// How to choose the initial set of centroids, among the observations of the first batch.
+    pub fn init(mut self, init: InitStrategy) -> Self {
+        self.init = init;
+        self
+    }
+
+    /// The source of randomness used for initialisation and batch sampling.
+    pub fn rng<R2: Rng>(self, rng: R2) -> MiniBatchKMeans<A, R2> {
+        MiniBatchKMeans {
+            n_clusters: self.n_clusters,
+            batch_size: self.batch_size,
+            tolerance: self.tolerance,
+            max_n_iterations: self.max_n_iterations,
+            init: self.init,
+            rng,
+            centroids: self.centroids,
+        }
+    }
+
+    /// The current centroids, as a `(n_clusters, n_features)` matrix.
+    ///
+    /// `None` if the model has not seen any observation yet.
+    pub fn centroids(&self) -> Option<Array2<A>> {
+        let centroids = self.centroids.as_ref()?;
+        let views: Vec<_> = centroids
+            .iter()
+            .map(|c| c.current_mean.view().insert_axis(Axis(0)))
+            .collect();
+        Some(stack(Axis(0), &views).expect("All centroids have the same length"))
+    }
+
+    /// Update the centroids with a new `batch` of observations,
+    /// a `(n_observations, n_features)` matrix.
+    ///
+    /// The first batch is used to initialise the centroids: it must contain at least
+    /// `n_clusters` observations.
+    ///
+    /// # Panics
+    ///
+    /// If `batch` does not have the same number of features as the previous ones.
+    pub fn partial_fit(
+        &mut self,
+        batch: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    ) -> Result<(), KMeansError> {
+        if self.n_clusters == 0 {
+            return Err(KMeansError::ZeroClusters);
+        }
+        if self.centroids.is_none() {
+            self.init.validate()?;
+            let n_observations = batch.len_of(Axis(0));
+            if n_observations < self.n_clusters {
+                return Err(KMeansError::NotEnoughObservations {
+                    n_observations,
+                    n_clusters: self.n_clusters,
+                });
+            }
+            let initial_centroids = self.init.initialise(self.n_clusters, batch, &mut self.rng);
+            // The initial centroids are picked from `batch`: they carry no weight of their own,
+            // otherwise they would be counted twice by the update below.
+            self.centroids = Some(
+                initial_centroids
+                    .genrows()
+                    .into_iter()
+                    .map(|c| IncrementalMean::seeded(c.to_owned()))
+                    .collect(),
+            );
+        }
+        let current_centroids = self.centroids().expect("Centroids have been initialised");
+        let centroids = self
+            .centroids
+            .as_mut()
+            .expect("Centroids have been initialised");
+
+        // All observations in the batch are assigned before any centroid moves
+        let memberships = compute_cluster_memberships(&current_centroids, batch);
+        for (observation, &cluster_index) in batch.genrows().into_iter().zip(memberships.iter()) {
+            centroids[cluster_index].update(&observation);
+        }
+        Ok(())
+    }
+
+    /// Cluster `observations`, a `(n_observations, n_features)` matrix, sampling
+    /// a batch of `batch_size` observations at each step.
+    ///
+    /// It starts from scratch, discarding the outcome of previous calls to
+    /// [`MiniBatchKMeans::partial_fit`].
+    pub fn fit(
+        &mut self,
+        observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    ) -> Result<FittedKMeans<A>, KMeansError> {
+        if self.batch_size == 0 {
+            return Err(KMeansError::ZeroBatchSize);
+        }
+        if self.max_n_iterations == 0 {
+            return Err(KMeansError::ZeroIterations);
+        }
+        if self.tolerance.is_nan() || self.tolerance < 0. {
+            return Err(KMeansError::InvalidTolerance(self.tolerance));
+        }
+
+        let tolerance = A::from_f64(self.tolerance).expect("Failed to convert f64 to float");
+        self.centroids = None;
+        let n_observations = observations.len_of(Axis(0));
+        let batch_size = self.batch_size.min(n_observations);
+        let mut history = Vec::new();
+        let mut converged = false;
+        while !converged && history.len() < self.max_n_iterations {
+            let indices = sample_indices(n_observations, batch_size, &mut self.rng);
+            let batch = observations.select(Axis(0), &indices);
+
+            let previous_centroids = self.centroids();
+            self.partial_fit(&batch)?;
+            let centroids = self
+                .centroids()
+                .expect("Centroids are set by `partial_fit`");
+
+            let centroid_shift = match previous_centroids {
+                Some(previous) => centroid_shift(&previous, &centroids),
+                None => A::infinity(),
+            };
+            let memberships = compute_cluster_memberships(&centroids, &batch);
+            history.push(IterationRecord {
+                centroid_shift,
+                // Computing the inertia on the whole dataset would defeat the purpose
+                inertia: inertia(&centroids, &batch, &memberships),
+            });
+            converged = centroid_shift < tolerance;
+        }
+
+        let centroids = self
+            .centroids()
+            .expect("Centroids are set by `partial_fit`");
+        let labels = compute_cluster_memberships(&centroids, observations);
+        let inertia = inertia(&centroids, observations, &labels);
+        Ok(FittedKMeans {
+            centroids,
+            labels,
+            inertia,
+            n_iterations: history.len(),
+            converged,
+            history,
+            n_skipped_distances: 0,
+            distance: Euclidean,
+        })
+    }
+
+    /// Assign each row of `observations` to the closest centroid.
+    ///
+    /// `None` if the model has not seen any observation yet.
+    ///
+    /// # Panics
+    ///
+    /// If `observations` does not have the same number of features as the training data.
+    pub fn predict(
+        &self,
+        observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    ) -> Option<Array1<usize>> {
+        let centroids = self.centroids()?;
+        Some(compute_cluster_memberships(&centroids, observations))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clustering::generation::generate_dataset;
+    use approx::assert_abs_diff_eq;
+    use ndarray::array;
+    use ndarray_stats::DeviationExt;
+
+    #[test]
+    fn recovers_well_separated_clusters() {
+        let expected_centroids = array![[10., 10.], [1., 12.], [20., 30.], [-20., 30.],];
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        let dataset = generate_dataset(1000, expected_centroids.view(), &mut rng);
+
+        let model = MiniBatchKMeans::new(4)
+            .batch_size(200)
+            .rng(rng)
+            .fit(&dataset)
+            .unwrap();
+
+        for expected in expected_centroids.genrows() {
+            assert!(model
+                .centroids
+                .genrows()
+                .into_iter()
+                .any(|c| c.l2_dist(&expected).unwrap() < 0.5));
+        }
+        assert_eq!(model.history.len(), model.n_iterations);
+    }
+
+    #[test]
+    fn partial_fit_moves_centroids_to_the_mean_of_their_observations() {
+        let mut model = MiniBatchKMeans::new(2)
+            .init(InitStrategy::Forgy)
+            .rng(Isaac64Rng::seed_from_u64(42));
+        assert!(model.centroids().is_none());
+
+        model.partial_fit(&array![[0.], [10.]]).unwrap();
+        model.partial_fit(&array![[1.], [2.], [12.]]).unwrap();
+
+        let mut centroids = model.centroids().unwrap().into_raw_vec();
+        centroids.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        // The initial centroids are picked from the first batch: they are counted once
+        assert_abs_diff_eq!(centroids[0], (0. + 1. + 2.) / 3.);
+        assert_abs_diff_eq!(centroids[1], (10. + 12.) / 2.);
+        assert_eq!(
+            model.predict(&array![[-5.], [50.]]).unwrap().to_vec(),
+            vec![
+                model.predict(&array![[0.]]).unwrap()[0],
+                model.predict(&array![[10.]]).unwrap()[0]
+            ]
+        );
+    }
+
+    #[test]
+    fn single_precision_partial_fit() {
+        let mut model = MiniBatchKMeans::new(2)
+            .init(InitStrategy::Forgy)
+            .rng(Isaac64Rng::seed_from_u64(42));
+
+        model.partial_fit(&array![[0f32], [10.]]).unwrap();
+        model.partial_fit(&array![[1f32], [2.], [12.]]).unwrap();
+
+        let centroids: Array2<f32> = model.centroids().unwrap();
+        assert_abs_diff_eq!(centroids.sum(), 3. / 3. + 22. / 2., epsilon = 1e-5);
+    }
+
+    #[test]
+    fn the_first_batch_must_be_large_enough() {
+        let mut model = MiniBatchKMeans::new(3);
+        assert_eq!(
+            model.partial_fit(&array![[0.], [10.]]).unwrap_err(),
+            KMeansError::NotEnoughObservations {
+                n_observations: 2,
+                n_clusters: 3
+            }
+        );
+        assert_eq!(
+            MiniBatchKMeans::new(1)
+                .batch_size(0)
+                .fit(&array![[0.]])
+                .unwrap_err(),
+            KMeansError::ZeroBatchSize
+        );
+        assert_eq!(
+            MiniBatchKMeans::new(1)
+                .init(InitStrategy::KMeansParallel {
+                    n_rounds: 0,
+                    oversampling_factor: 2.,
+                })
+                .partial_fit(&array![[0.]])
+                .unwrap_err(),
+            KMeansError::ZeroRounds
+        );
+    }
+}
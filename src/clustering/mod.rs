@@ -0,0 +1,68 @@
+//! A reference implementation of K-means clustering, built with `ndarray`.
+//!
+//! The koans walk you through writing each of these routines yourself: this module
+//! collects the solutions in a place where they can be used by downstream code and benchmarks.
+//! Its layout mirrors the chapters of the path to enlightenment:
+//! - [`generation`]: synthetic datasets of normally distributed clusters (and trickier shapes),
+//!   with their ground-truth labels;
+//! - [`initialisation`]: how to pick the initial set of centroids;
+//! - [`assignment`]: assign each observation to its closest centroid,
+//!   according to the euclidean distance or any other [`distance`];
+//! - [`update`]: recompute each centroid as the mean of the observations assigned to it;
+//! - [`k_means`](fn@k_means): the whole algorithm, alternating assignment and update until convergence.
+//!
+//! [`KMeans`] wraps the whole pipeline in an estimator with a builder-like API,
+//! with [`Algorithm`]s that skip most of the distance computations of the assignment step.
+//! [`MiniBatchKMeans`] is its counterpart for datasets that do not fit in memory.
+//! Not sure how many clusters to look for? Check out [`model_selection`], and [`metrics`]
+//! to evaluate the outcome. [`alignment`] matches the fitted centroids with a reference set,
+//! e.g. the one a synthetic dataset was generated from.
+//!
+//! Everything but dataset generation works with both `f32` and `f64` observations:
+//! see [`Float`].
+//!
+//! ```
+//! use koans::clustering::{compute_cluster_memberships, generate_dataset, k_means};
+//! use ndarray::array;
+//! use ndarray_rand::rand::SeedableRng;
+//! use rand_isaac::Isaac64Rng;
+//!
+//! let mut rng = Isaac64Rng::seed_from_u64(42);
+//! let expected_centroids = array![[10., 10.], [1., 12.], [20., 30.], [-20., 30.]];
+//! let dataset = generate_dataset(100, expected_centroids.view(), &mut rng);
+//!
+//! let centroids = k_means(4, &dataset, &mut rng, 1e-5, 200);
+//! let memberships = compute_cluster_memberships(&centroids, &dataset);
+//! assert_eq!(memberships.len(), 400);
+//! ```
+mod accelerated;
+pub mod alignment;
+pub mod assignment;
+pub mod distance;
+mod error;
+mod float;
+pub mod generation;
+pub mod initialisation;
+mod k_means;
+pub mod metrics;
+mod mini_batch;
+pub mod model_selection;
+pub mod update;
+
+pub use accelerated::Algorithm;
+pub use assignment::{
+    closest_centroid, closest_centroid_with, compute_cluster_memberships,
+    compute_cluster_memberships_vectorised, compute_cluster_memberships_with, euclidean_distance,
+    pairwise_squared_distances,
+};
+pub use distance::Distance;
+pub use error::KMeansError;
+pub use float::Float;
+pub use generation::{generate_cluster, generate_dataset, generate_labelled_dataset};
+pub use initialisation::{get_random_centroids, k_means_parallel, k_means_plus_plus, InitStrategy};
+pub use k_means::{inertia, inertia_with, k_means, FittedKMeans, IterationRecord, KMeans};
+pub use mini_batch::MiniBatchKMeans;
+pub use update::{
+    compute_centroids, compute_centroids_hashmap, update_centroids, EmptyClusterPolicy,
+    IncrementalMean, RunningStatistics,
+};
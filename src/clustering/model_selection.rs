@@ -0,0 +1,369 @@
+//! How many clusters are there?
+//!
+//! K-means needs the number of clusters, `k`, upfront. When we do not know it, we can fit a model
+//! for each `k` in a range and compare them using a few criteria:
+//! - the elbow method: the inertia always decreases as `k` grows, but the decrease flattens out
+//!   once we go past the "natural" number of clusters;
+//! - the mean [silhouette coefficient](super::metrics::silhouette_score_with): how well
+//!   separated the clusters are;
+//! - the gap statistic (Tibshirani et al., 2001): how much lower the inertia is compared to
+//!   datasets without any cluster structure, drawn uniformly at random.
+use super::distance::Distance;
+use super::error::KMeansError;
+use super::k_means::KMeans;
+use super::metrics::silhouette_score_with;
+use super::Float;
+use ndarray::{Array, Array1, Array2, ArrayBase, Axis, Data, Ix2};
+use ndarray_rand::rand::{Rng, SeedableRng};
+use ndarray_rand::rand_distr::Uniform;
+use ndarray_rand::RandomExt;
+use rand_isaac::Isaac64Rng;
+use std::ops::RangeInclusive;
+
+/// The criteria available to pick the number of clusters.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Criterion {
+    /// The `k` at the "knee" of the inertia curve: the point farthest below the straight line
+    /// joining the first and the last point of the curve (after rescaling both axes to `[0, 1]`).
+    Elbow,
+    /// The `k` with the highest mean silhouette coefficient.
+    Silhouette,
+    /// The smallest `k` such that `gap(k) >= gap(k + 1) - gap_std(k + 1)`,
+    /// as recommended by Tibshirani et al. (the largest `k` in the range, if there is none).
+    #[default]
+    Gap,
+}
+
+/// Pick the number of clusters by fitting K-means for each `k` in a range,
+/// configured with a builder-like API.
+///
+/// ```
+/// use koans::clustering::model_selection::{Criterion, KSelection};
+/// use koans::clustering::{generate_dataset, KMeans};
+/// use ndarray::array;
+/// use ndarray_rand::rand::SeedableRng;
+/// use rand_isaac::Isaac64Rng;
+///
+/// let mut rng = Isaac64Rng::seed_from_u64(42);
+/// let centroids = array![[10., 10.], [1., 12.], [20., 30.], [-20., 30.]];
+/// let dataset = generate_dataset(50, centroids.view(), &mut rng);
+///
+/// let k_means = KMeans::new(1).n_init(3).rng(Isaac64Rng::seed_from_u64(7));
+/// let report = KSelection::new(1..=6)
+///     .rng(rng)
+///     .fit(k_means, &dataset)
+///     .expect("Failed to fit K-means");
+///
+/// assert_eq!(report.recommended_k, 4);
+/// assert_eq!(report.recommend(Criterion::Silhouette), 4);
+/// assert_eq!(report.scores.len(), 6);
+/// ```
+pub struct KSelection<R: Rng = Isaac64Rng> {
+    k_range: RangeInclusive<usize>,
+    n_references: usize,
+    criterion: Criterion,
+    rng: R,
+}
+
+impl KSelection<Isaac64Rng> {
+    /// Compare the values of `k` in `k_range`, using the default configuration:
+    /// - `n_references`: 10;
+    /// - `criterion`: [`Criterion::Gap`];
+    /// - `rng`: an `Isaac64Rng` seeded from the operating system's source of randomness.
+    pub fn new(k_range: RangeInclusive<usize>) -> Self {
+        Self {
+            k_range,
+            n_references: 10,
+            criterion: Criterion::default(),
+            rng: Isaac64Rng::from_entropy(),
+        }
+    }
+}
+
+impl<R: Rng> KSelection<R> {
+    /// How many uniform reference datasets to draw for the gap statistic.
+    pub fn n_references(mut self, n_references: usize) -> Self {
+        self.n_references = n_references;
+        self
+    }
+
+    /// The criterion used to fill in [`KSelectionReport::recommended_k`].
+    pub fn criterion(mut self, criterion: Criterion) -> Self {
+        self.criterion = criterion;
+        self
+    }
+
+    /// The source of randomness used to draw the reference datasets.
+    pub fn rng<R2: Rng>(self, rng: R2) -> KSelection<R2> {
+        KSelection {
+            k_range: self.k_range,
+            n_references: self.n_references,
+            criterion: self.criterion,
+            rng,
+        }
+    }
+
+    /// Fit `k_means` to `observations`, a `(n_observations, n_features)` matrix,
+    /// for each `k` in the range, overriding its number of clusters.
+    ///
+    /// The gap statistic needs `n_references` more fits for each `k`, on reference datasets
+    /// drawn uniformly at random within the bounding box of `observations`.
+    pub fn fit<A: Float, K: Rng, D: Distance>(
+        &mut self,
+        k_means: KMeans<K, D>,
+        observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    ) -> Result<KSelectionReport<A>, KMeansError> {
+        let (&min_k, &max_k) = (self.k_range.start(), self.k_range.end());
+        if min_k == 0 || min_k >= max_k {
+            return Err(KMeansError::InvalidClusterRange { min_k, max_k });
+        }
+        if self.n_references == 0 {
+            return Err(KMeansError::ZeroReferenceDatasets);
+        }
+
+        let references: Vec<Array2<A>> = (0..self.n_references)
+            .map(|_| uniform_reference(observations, &mut self.rng))
+            .collect();
+        let log = |x: A| x.max(A::min_positive_value()).ln();
+
+        let mut k_means = k_means;
+        let mut scores = Vec::with_capacity(max_k - min_k + 1);
+        for n_clusters in self.k_range.clone() {
+            k_means = k_means.n_clusters(n_clusters);
+            let model = k_means.fit(observations)?;
+            let silhouette = silhouette_score_with(observations, &model.labels, &model.distance);
+
+            let reference_log_inertias = references
+                .iter()
+                .map(|reference| Ok(log(k_means.fit(reference)?.inertia)))
+                .collect::<Result<Array1<A>, KMeansError>>()?;
+            let expected_log_inertia = reference_log_inertias
+                .mean()
+                .expect("There is at least one reference dataset");
+            let n_references =
+                A::from_usize(self.n_references).expect("Failed to convert usize to float");
+            let variance = reference_log_inertias
+                .mapv(|x| (x - expected_log_inertia).powi(2))
+                .sum()
+                / n_references;
+            let gap_std = (variance * (A::one() + A::one() / n_references)).sqrt();
+
+            scores.push(KScore {
+                n_clusters,
+                inertia: model.inertia,
+                silhouette,
+                gap: expected_log_inertia - log(model.inertia),
+                gap_std,
+            });
+        }
+
+        let mut report = KSelectionReport {
+            scores,
+            recommended_k: 0,
+        };
+        report.recommended_k = report.recommend(self.criterion);
+        Ok(report)
+    }
+}
+
+/// How a K-means model with `n_clusters` clusters scored on each criterion.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KScore<A = f64> {
+    pub n_clusters: usize,
+    /// The inertia of the best fit, see [`FittedKMeans::inertia`](super::FittedKMeans::inertia).
+    pub inertia: A,
+    /// The mean silhouette coefficient: `None` if there is a single cluster.
+    pub silhouette: Option<A>,
+    /// The expected log-inertia of the uniform reference datasets minus the log-inertia
+    /// of the observations.
+    pub gap: A,
+    /// The standard deviation of the reference log-inertias, scaled by `sqrt(1 + 1 / n_references)`
+    /// to account for the simulation error.
+    pub gap_std: A,
+}
+
+/// The outcome of [`KSelection::fit`].
+#[derive(Debug, Clone)]
+pub struct KSelectionReport<A = f64> {
+    /// One entry for each `k` in the range, in increasing order.
+    pub scores: Vec<KScore<A>>,
+    /// The number of clusters recommended by the criterion chosen with [`KSelection::criterion`].
+    pub recommended_k: usize,
+}
+
+impl<A: Float> KSelectionReport<A> {
+    /// The number of clusters recommended by `criterion`.
+    pub fn recommend(&self, criterion: Criterion) -> usize {
+        let scores = &self.scores;
+        match criterion {
+            Criterion::Elbow => {
+                let first = scores.first().expect("The range of `k` is not empty");
+                let last = scores.last().expect("The range of `k` is not empty");
+                let inertia_range = first.inertia - last.inertia;
+                if inertia_range <= A::zero() {
+                    return first.n_clusters;
+                }
+                let k_range = A::from_usize(last.n_clusters - first.n_clusters)
+                    .expect("Failed to convert usize to float");
+                // How far each point is below the line joining the first and the last one,
+                // once both axes are rescaled to [0, 1]
+                let depth = |score: &KScore<A>| {
+                    let x = A::from_usize(score.n_clusters - first.n_clusters)
+                        .expect("Failed to convert usize to float")
+                        / k_range;
+                    let y = (score.inertia - last.inertia) / inertia_range;
+                    (A::one() - x) - y
+                };
+                argmax(scores.iter().map(|score| (score.n_clusters, depth(score))))
+            }
+            Criterion::Silhouette => argmax(scores.iter().filter_map(|score| {
+                score
+                    .silhouette
+                    .map(|silhouette| (score.n_clusters, silhouette))
+            })),
+            Criterion::Gap => {
+                scores
+                    .windows(2)
+                    .find(|pair| pair[0].gap >= pair[1].gap - pair[1].gap_std)
+                    .map_or_else(
+                        || scores.last().expect("The range of `k` is not empty"),
+                        |pair| &pair[0],
+                    )
+                    .n_clusters
+            }
+        }
+    }
+
+    /// The score table as a `(n_k, 5)` matrix, with columns `k`, inertia, silhouette
+    /// (`NaN` if not defined), gap and gap standard deviation.
+    ///
+    /// Handy to plot the curves in the notebooks in the `python` folder.
+    pub fn scores_array(&self) -> Array2<A> {
+        let mut table = Array2::zeros((self.scores.len(), 5));
+        for (mut row, score) in table.genrows_mut().into_iter().zip(&self.scores) {
+            row[0] = A::from_usize(score.n_clusters).expect("Failed to convert usize to float");
+            row[1] = score.inertia;
+            row[2] = score.silhouette.unwrap_or_else(A::nan);
+            row[3] = score.gap;
+            row[4] = score.gap_std;
+        }
+        table
+    }
+}
+
+// The `k` with the highest value, the smallest one on ties.
+fn argmax<A: Float>(values: impl Iterator<Item = (usize, A)>) -> usize {
+    values
+        .fold(None, |best: Option<(usize, A)>, (k, value)| match best {
+            Some((_, best_value)) if best_value >= value => best,
+            _ => Some((k, value)),
+        })
+        .expect("There is at least one `k` to choose from")
+        .0
+}
+
+/// A dataset with the same shape as `observations`, drawn uniformly at random within the range
+/// of each of its features.
+fn uniform_reference<A: Float>(
+    observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    rng: &mut impl Rng,
+) -> Array2<A> {
+    let to_f64 = |x: A| x.to_f64().expect("Failed to convert float to f64");
+    let mut reference = Array2::zeros(observations.dim());
+    for (mut column, feature) in reference
+        .gencolumns_mut()
+        .into_iter()
+        .zip(observations.gencolumns())
+    {
+        let min = feature.fold(A::infinity(), |a, &b| a.min(b));
+        let max = feature.fold(A::neg_infinity(), |a, &b| a.max(b));
+        if min < max {
+            let values: Array1<f64> =
+                Array::random_using(column.len(), Uniform::new(to_f64(min), to_f64(max)), rng);
+            column
+                .assign(&values.mapv(|x| A::from_f64(x).expect("Failed to convert f64 to float")));
+        } else {
+            column.fill(min);
+        }
+    }
+    reference
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clustering::generation::generate_dataset;
+    use ndarray::array;
+
+    fn four_clusters() -> Array2<f64> {
+        let centroids = array![[0., 0.], [20., 0.], [0., 20.], [20., 20.]];
+        generate_dataset(100, centroids.view(), &mut Isaac64Rng::seed_from_u64(42))
+    }
+
+    #[test]
+    fn all_criteria_find_the_four_clusters() {
+        let dataset = four_clusters();
+        let k_means = KMeans::new(1).n_init(3).rng(Isaac64Rng::seed_from_u64(7));
+
+        let report = KSelection::new(1..=8)
+            .rng(Isaac64Rng::seed_from_u64(42))
+            .fit(k_means, &dataset)
+            .unwrap();
+
+        let ks: Vec<usize> = report.scores.iter().map(|score| score.n_clusters).collect();
+        assert_eq!(ks, (1..=8).collect::<Vec<_>>());
+        assert!(report
+            .scores
+            .windows(2)
+            .all(|pair| pair[1].inertia <= pair[0].inertia));
+        assert_eq!(report.scores[0].silhouette, None);
+        assert_eq!(report.recommended_k, 4);
+        for &criterion in &[Criterion::Elbow, Criterion::Silhouette, Criterion::Gap] {
+            assert_eq!(report.recommend(criterion), 4, "{:?}", criterion);
+        }
+
+        let table = report.scores_array();
+        assert_eq!(table.dim(), (8, 5));
+        assert!(table[[0, 2]].is_nan());
+        assert_eq!(table.column(1).to_vec(), {
+            report.scores.iter().map(|s| s.inertia).collect::<Vec<_>>()
+        });
+    }
+
+    #[test]
+    fn uniform_data_has_no_gap() {
+        let dataset: Array2<f64> = Array::random_using(
+            (200, 2),
+            Uniform::new(0., 1.),
+            &mut Isaac64Rng::seed_from_u64(42),
+        );
+        let k_means = KMeans::new(1).n_init(3).rng(Isaac64Rng::seed_from_u64(7));
+
+        let report = KSelection::new(1..=5)
+            .rng(Isaac64Rng::seed_from_u64(42))
+            .fit(k_means, &dataset)
+            .unwrap();
+
+        assert_eq!(report.recommend(Criterion::Gap), 1);
+    }
+
+    #[test]
+    fn invalid_ranges_are_rejected() {
+        let dataset = four_clusters();
+        for &(min_k, max_k) in &[(0, 4), (3, 3), (5, 2)] {
+            assert_eq!(
+                KSelection::new(min_k..=max_k)
+                    .fit(KMeans::new(1), &dataset)
+                    .unwrap_err(),
+                KMeansError::InvalidClusterRange { min_k, max_k }
+            );
+        }
+        assert_eq!(
+            KSelection::new(1..=4)
+                .n_references(0)
+                .fit(KMeans::new(1), &dataset)
+                .unwrap_err(),
+            KMeansError::ZeroReferenceDatasets
+        );
+    }
+}
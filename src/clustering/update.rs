@@ -0,0 +1,951 @@
+//! Recompute the centroid (= the mean) of each cluster.
+use super::distance::{Centre, Distance};
+use super::error::KMeansError;
+use super::Float;
+use ndarray::{Array1, Array2, ArrayBase, ArrayView1, Axis, Data, Ix1, Ix2, Zip};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// What to do when no observation is assigned to one of the clusters.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum EmptyClusterPolicy {
+    /// Give up, returning [`KMeansError::EmptyCluster`].
+    Error,
+    /// Leave the centroid where it was before the update step.
+    KeepPrevious,
+    /// Move the centroid to the observation farthest from the centroid of its own cluster,
+    /// splitting the most spread-out cluster.
+    #[default]
+    ReseedFarthest,
+}
+
+/// The mean of a set of observations, computed one observation at a time:
+///
+/// ```text
+/// new_mean = current_mean + (new_observation - current_mean) / (n + 1)
+/// ```
+///
+/// Observations can also carry a weight, e.g. how many times they occur in the dataset:
+///
+/// ```text
+/// new_mean = current_mean + (new_observation - current_mean) * weight / (total_weight + weight)
+/// ```
+pub struct IncrementalMean<A = f64> {
+    pub current_mean: Array1<A>,
+    pub n_observations: usize,
+    /// The sum of the weights of the observations: `n_observations` if they are not weighted.
+    pub total_weight: A,
+}
+
+impl<A: Float> IncrementalMean<A> {
+    pub fn new(first_observation: Array1<A>) -> Self {
+        Self::new_weighted(first_observation, A::one())
+    }
+
+    /// # Panics
+    ///
+    /// If `weight` is not positive.
+    pub fn new_weighted(first_observation: Array1<A>, weight: A) -> Self {
+        assert!(weight > A::zero(), "Weights must be positive");
+        Self {
+            current_mean: first_observation,
+            n_observations: 1,
+            total_weight: weight,
+        }
+    }
+
+    /// The mean of no observations yet, starting at `position` (e.g. an initial centroid).
+    ///
+    /// `position` carries no weight: the first update moves the mean all the way
+    /// to the new observation, as if it had been built with [`IncrementalMean::new`].
+    pub fn seeded(position: Array1<A>) -> Self {
+        Self {
+            current_mean: position,
+            n_observations: 0,
+            total_weight: A::zero(),
+        }
+    }
+
+    pub fn update(&mut self, new_observation: &ArrayBase<impl Data<Elem = A>, Ix1>) {
+        self.update_weighted(new_observation, A::one())
+    }
+
+    /// # Panics
+    ///
+    /// If `weight` is not positive.
+    pub fn update_weighted(
+        &mut self,
+        new_observation: &ArrayBase<impl Data<Elem = A>, Ix1>,
+        weight: A,
+    ) {
+        assert!(weight > A::zero(), "Weights must be positive");
+        self.n_observations += 1;
+        self.total_weight += weight;
+        let shift = (new_observation - &self.current_mean) * weight / self.total_weight;
+        self.current_mean += &shift;
+    }
+
+    /// Combine with the mean of another, disjoint, set of observations:
+    ///
+    /// ```text
+    /// new_mean = current_mean + (other_mean - current_mean) * other_weight / (total_weight + other_weight)
+    /// ```
+    ///
+    /// Merging a [seeded](IncrementalMean::seeded) mean, with no observations, is a no-op.
+    pub fn merge(&mut self, other: IncrementalMean<A>) {
+        if other.n_observations == 0 {
+            return;
+        }
+        self.n_observations += other.n_observations;
+        self.total_weight += other.total_weight;
+        let shift =
+            (other.current_mean - &self.current_mean) * other.total_weight / self.total_weight;
+        self.current_mean += &shift;
+    }
+}
+
+/// Running statistics of a set of observations, computed one observation at a time:
+/// for each feature, the mean, the variance (using Welford's algorithm) and the range.
+///
+/// The variance is updated from the deviations of each new observation from the mean,
+/// before and after taking it into account:
+///
+/// ```text
+/// new_mean = current_mean + (new_observation - current_mean) * weight / (total_weight + weight)
+/// new_m2 = current_m2 + weight * (new_observation - current_mean) * (new_observation - new_mean)
+/// ```
+///
+/// Unlike the textbook `E[x²] - E[x]²`, it does not lose all precision when the variance
+/// is tiny compared to the mean. The mean is updated exactly as in [`IncrementalMean`].
+///
+/// Statistics of disjoint sets of observations can be [merged](RunningStatistics::merge),
+/// e.g. to summarise chunks of a dataset in parallel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunningStatistics<A = f64> {
+    /// The mean of each feature.
+    pub mean: Array1<A>,
+    /// The smallest value of each feature.
+    pub min: Array1<A>,
+    /// The largest value of each feature.
+    pub max: Array1<A>,
+    pub n_observations: usize,
+    /// The sum of the weights of the observations: `n_observations` if they are not weighted.
+    pub total_weight: A,
+    // The (weighted) sum of the squared deviations from the mean, for each feature
+    m2: Array1<A>,
+}
+
+impl<A: Float> RunningStatistics<A> {
+    pub fn new(first_observation: Array1<A>) -> Self {
+        Self::new_weighted(first_observation, A::one())
+    }
+
+    /// # Panics
+    ///
+    /// If `weight` is not positive.
+    pub fn new_weighted(first_observation: Array1<A>, weight: A) -> Self {
+        assert!(weight > A::zero(), "Weights must be positive");
+        Self {
+            min: first_observation.clone(),
+            max: first_observation.clone(),
+            m2: Array1::zeros(first_observation.len()),
+            mean: first_observation,
+            n_observations: 1,
+            total_weight: weight,
+        }
+    }
+
+    pub fn update(&mut self, new_observation: &ArrayBase<impl Data<Elem = A>, Ix1>) {
+        self.update_weighted(new_observation, A::one())
+    }
+
+    /// # Panics
+    ///
+    /// If `weight` is not positive or if `new_observation` does not have the same number
+    /// of features as the previous observations.
+    pub fn update_weighted(
+        &mut self,
+        new_observation: &ArrayBase<impl Data<Elem = A>, Ix1>,
+        weight: A,
+    ) {
+        assert!(weight > A::zero(), "Weights must be positive");
+        self.n_observations += 1;
+        self.total_weight += weight;
+        let total_weight = self.total_weight;
+        Zip::from(&mut self.mean)
+            .and(&mut self.m2)
+            .and(&mut self.min)
+            .and(&mut self.max)
+            .and(new_observation)
+            .apply(|mean, m2, min, max, &x| {
+                let deviation = x - *mean;
+                *mean += deviation * weight / total_weight;
+                *m2 += weight * deviation * (x - *mean);
+                *min = min.min(x);
+                *max = max.max(x);
+            });
+    }
+
+    /// Combine with the statistics of another, disjoint, set of observations
+    /// (Chan et al., 1979):
+    ///
+    /// ```text
+    /// delta = other_mean - current_mean
+    /// new_mean = current_mean + delta * other_weight / (total_weight + other_weight)
+    /// new_m2 = current_m2 + other_m2 + delta² * total_weight * other_weight / (total_weight + other_weight)
+    /// ```
+    pub fn merge(&mut self, other: RunningStatistics<A>) {
+        let weight = self.total_weight;
+        self.n_observations += other.n_observations;
+        self.total_weight += other.total_weight;
+        let total_weight = self.total_weight;
+        Zip::from(&mut self.mean)
+            .and(&mut self.m2)
+            .and(&other.mean)
+            .and(&other.m2)
+            .apply(|mean, m2, &other_mean, &other_m2| {
+                let delta = other_mean - *mean;
+                *mean += delta * other.total_weight / total_weight;
+                *m2 += other_m2 + delta * delta * weight * other.total_weight / total_weight;
+            });
+        Zip::from(&mut self.min)
+            .and(&mut self.max)
+            .and(&other.min)
+            .and(&other.max)
+            .apply(|min, max, &other_min, &other_max| {
+                *min = min.min(other_min);
+                *max = max.max(other_max);
+            });
+    }
+
+    /// The variance of each feature: the sum of the (weighted) squared deviations
+    /// from the mean, divided by `total_weight - ddof`.
+    ///
+    /// As in `ndarray`'s `var_axis`, use `ddof = 0` for the population variance and `ddof = 1`
+    /// for the unbiased estimator of the variance of the underlying distribution
+    /// (if weights are counts of repeated observations).
+    ///
+    /// # Panics
+    ///
+    /// If `ddof` is not smaller than `total_weight`.
+    pub fn variance(&self, ddof: A) -> Array1<A> {
+        assert!(
+            ddof < self.total_weight,
+            "`ddof` must be smaller than the total weight"
+        );
+        &self.m2 / (self.total_weight - ddof)
+    }
+
+    /// The standard deviation of each feature, see [`RunningStatistics::variance`].
+    pub fn std(&self, ddof: A) -> Array1<A> {
+        self.variance(ddof).mapv(A::sqrt)
+    }
+}
+
+/// Iterate over our observations and capture the new centroids in a
+/// `cluster_index => new centroid` map.
+///
+/// Each entry also tracks the spread of its cluster: see [`RunningStatistics`].
+///
+/// Clusters without observations do not get an entry.
+///
+/// With the `rayon` feature enabled, each chunk of
+/// [`PARALLEL_CHUNK_SIZE`](super::assignment::PARALLEL_CHUNK_SIZE) observations is
+/// summarised in parallel and the partial statistics are then [merged](RunningStatistics::merge),
+/// in order. Rounding happens in a different order: for datasets with more than one chunk
+/// the means are not bit-identical to the sequential ones, but they agree within a relative
+/// tolerance of a few `A::epsilon()` per chunk. They do not depend on the number of threads.
+pub fn compute_centroids_hashmap<A: Float>(
+    // (n_observations, n_features)
+    observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    // (n_observations,)
+    cluster_memberships: &ArrayBase<impl Data<Elem = usize>, Ix1>,
+) -> HashMap<usize, RunningStatistics<A>> {
+    weighted_centroids_hashmap(observations, cluster_memberships, None)
+}
+
+// Like `compute_centroids_hashmap`, weighting each observation by `sample_weight` (if any).
+fn weighted_centroids_hashmap<A: Float>(
+    observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    cluster_memberships: &ArrayBase<impl Data<Elem = usize>, Ix1>,
+    sample_weight: Option<ArrayView1<f64>>,
+) -> HashMap<usize, RunningStatistics<A>> {
+    #[cfg(feature = "rayon")]
+    {
+        use super::assignment::row_chunks;
+        use ndarray::s;
+        use rayon::prelude::*;
+
+        let (observations, cluster_memberships) = (observations.view(), cluster_memberships.view());
+        let partial_statistics: Vec<HashMap<usize, RunningStatistics<A>>> =
+            row_chunks(observations.nrows())
+                .into_par_iter()
+                .map(|rows| {
+                    sequential_centroids_hashmap(
+                        &observations.slice(s![rows.clone(), ..]),
+                        &cluster_memberships.slice(s![rows.clone()]),
+                        sample_weight.map(|sample_weight| sample_weight.slice_move(s![rows])),
+                    )
+                })
+                .collect();
+        let mut new_centroids: HashMap<usize, RunningStatistics<A>> = HashMap::new();
+        for partial_statistics in partial_statistics {
+            for (cluster_index, statistics) in partial_statistics {
+                match new_centroids.get_mut(&cluster_index) {
+                    Some(current_statistics) => current_statistics.merge(statistics),
+                    None => {
+                        new_centroids.insert(cluster_index, statistics);
+                    }
+                }
+            }
+        }
+        new_centroids
+    }
+    #[cfg(not(feature = "rayon"))]
+    sequential_centroids_hashmap(observations, cluster_memberships, sample_weight)
+}
+
+fn sequential_centroids_hashmap<A: Float>(
+    observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    cluster_memberships: &ArrayBase<impl Data<Elem = usize>, Ix1>,
+    sample_weight: Option<ArrayView1<f64>>,
+) -> HashMap<usize, RunningStatistics<A>> {
+    let mut new_centroids: HashMap<usize, RunningStatistics<A>> = HashMap::new();
+    for (index, (observation, cluster_membership)) in observations
+        .genrows()
+        .into_iter()
+        .zip(cluster_memberships.iter())
+        .enumerate()
+    {
+        let weight = sample_weight.map_or(A::one(), |sample_weight| to_float(sample_weight[index]));
+        if let Some(statistics) = new_centroids.get_mut(cluster_membership) {
+            statistics.update_weighted(&observation, weight);
+        } else {
+            new_centroids.insert(
+                *cluster_membership,
+                RunningStatistics::new_weighted(observation.to_owned(), weight),
+            );
+        }
+    }
+    new_centroids
+}
+
+/// The new centroids as a `(n_centroids, n_features)` matrix, where the i-th row
+/// is the mean of the observations in the i-th cluster.
+///
+/// The rows of clusters without observations are left filled with zeros:
+/// check out [`update_centroids`] for alternatives.
+pub fn compute_centroids<A: Float>(
+    n_centroids: usize,
+    // (n_observations, n_features)
+    observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    // (n_observations,)
+    cluster_memberships: &ArrayBase<impl Data<Elem = usize>, Ix1>,
+) -> Array2<A> {
+    let centroids_hashmap = compute_centroids_hashmap(observations, cluster_memberships);
+
+    let (_, n_features) = observations.dim();
+    let mut centroids: Array2<A> = Array2::zeros((n_centroids, n_features));
+    for (centroid_index, centroid) in centroids_hashmap.into_iter() {
+        centroids.row_mut(centroid_index).assign(&centroid.mean);
+    }
+    centroids
+}
+
+/// Like [`compute_centroids`], but:
+/// - each centroid is updated to the [`Centre`] of its cluster required by `distance`
+///   (e.g. the median for [`Manhattan`](super::distance::Manhattan));
+/// - clusters without observations are handled according to `policy`
+///   instead of being left filled with zeros.
+///
+/// `previous_centroids` is the `(n_centroids, n_features)` matrix the cluster memberships
+/// were computed from.
+///
+/// Reseeding is deterministic: empty clusters are processed in index order, each one taking
+/// the farthest observation not already picked by another empty cluster (the lowest index
+/// on ties).
+///
+/// If `sample_weight` is provided, each observation counts as many times as its weight:
+/// a weight of 2 has the same effect as duplicating the observation.
+/// Weights must be positive.
+pub fn update_centroids<A: Float>(
+    // (n_centroids, n_features)
+    previous_centroids: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    // (n_observations, n_features)
+    observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    // (n_observations,)
+    cluster_memberships: &ArrayBase<impl Data<Elem = usize>, Ix1>,
+    // (n_observations,)
+    sample_weight: Option<ArrayView1<f64>>,
+    policy: EmptyClusterPolicy,
+    distance: &impl Distance,
+) -> Result<Array2<A>, KMeansError> {
+    let mut centroids = previous_centroids.to_owned();
+    let mut empty_clusters = vec![];
+    let centres = compute_centres(
+        centroids.nrows(),
+        observations,
+        cluster_memberships,
+        sample_weight,
+        distance.centre(),
+    );
+    for (centroid_index, (mut centroid, centre)) in
+        centroids.genrows_mut().into_iter().zip(centres).enumerate()
+    {
+        match centre {
+            Some(centre) => centroid.assign(&centre),
+            None => empty_clusters.push(centroid_index),
+        }
+    }
+
+    match policy {
+        _ if empty_clusters.is_empty() => {}
+        EmptyClusterPolicy::Error => return Err(KMeansError::EmptyCluster(empty_clusters[0])),
+        EmptyClusterPolicy::KeepPrevious => {}
+        EmptyClusterPolicy::ReseedFarthest => {
+            let mut distances: Vec<(usize, A)> = observations
+                .genrows()
+                .into_iter()
+                .zip(cluster_memberships.iter())
+                .map(|(observation, &cluster_index)| {
+                    distance.distance(&observation, &centroids.row(cluster_index))
+                })
+                .enumerate()
+                .collect();
+            // A stable sort keeps the lowest index first on ties
+            distances.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+            for (cluster_index, (observation_index, _)) in empty_clusters.into_iter().zip(distances)
+            {
+                centroids
+                    .row_mut(cluster_index)
+                    .assign(&observations.row(observation_index));
+            }
+        }
+    }
+    Ok(centroids)
+}
+
+/// The centre of each one of the `n_centroids` clusters, `None` for empty clusters.
+fn compute_centres<A: Float>(
+    n_centroids: usize,
+    observations: &ArrayBase<impl Data<Elem = A>, Ix2>,
+    cluster_memberships: &ArrayBase<impl Data<Elem = usize>, Ix1>,
+    sample_weight: Option<ArrayView1<f64>>,
+    centre: Centre,
+) -> Vec<Option<Array1<A>>> {
+    match centre {
+        Centre::Mean | Centre::NormalisedMean => {
+            let mut means =
+                weighted_centroids_hashmap(observations, cluster_memberships, sample_weight);
+            (0..n_centroids)
+                .map(|index| {
+                    let mean = means.remove(&index)?.mean;
+                    if centre == Centre::Mean {
+                        return Some(mean);
+                    }
+                    let norm = mean.dot(&mean).sqrt();
+                    Some(if norm > A::zero() { mean / norm } else { mean })
+                })
+                .collect()
+        }
+        Centre::Median => {
+            let mut clusters: Vec<Vec<usize>> = vec![vec![]; n_centroids];
+            for (observation_index, &cluster_index) in cluster_memberships.iter().enumerate() {
+                clusters[cluster_index].push(observation_index);
+            }
+            clusters
+                .into_iter()
+                .map(|indices| {
+                    if indices.is_empty() {
+                        return None;
+                    }
+                    let cluster = observations.select(Axis(0), &indices);
+                    let weights: Vec<f64> = match sample_weight {
+                        Some(sample_weight) => indices.iter().map(|&i| sample_weight[i]).collect(),
+                        None => vec![1.; indices.len()],
+                    };
+                    Some(cluster.map_axis(Axis(0), |feature| {
+                        median(
+                            feature
+                                .iter()
+                                .cloned()
+                                .zip(weights.iter().cloned())
+                                .collect(),
+                        )
+                    }))
+                })
+                .collect()
+        }
+    }
+}
+
+// The weighted median of a set of `(value, weight)` pairs: the average of the two middle values
+// when the total weight is split evenly between them, as for an even number of observations.
+fn median<A: Float>(mut values: Vec<(A, f64)>) -> A {
+    values.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let half_weight = values.iter().map(|(_, weight)| weight).sum::<f64>() / 2.;
+    let mut cumulative_weight = 0.;
+    let mut lower = None;
+    for (value, weight) in values {
+        cumulative_weight += weight;
+        if lower.is_none() && cumulative_weight >= half_weight {
+            lower = Some(value);
+        }
+        if cumulative_weight > half_weight {
+            let lower = lower.expect("The lower median comes first");
+            return if lower == value {
+                value
+            } else {
+                (lower + value) / (A::one() + A::one())
+            };
+        }
+    }
+    unreachable!("The cumulative weight reaches the total weight")
+}
+
+// Observations are weighted by `f64`s, to be converted to the type of the observations.
+fn to_float<A: Float>(weight: f64) -> A {
+    A::from_f64(weight).expect("Failed to convert f64 to float")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clustering::distance::{Cosine, Euclidean, Manhattan};
+    use approx::{assert_abs_diff_eq, assert_relative_eq};
+    use ndarray::{array, s, stack, Array, ArrayView2, Axis};
+    use ndarray_rand::rand::SeedableRng;
+    use ndarray_rand::rand_distr::Uniform;
+    use ndarray_rand::RandomExt;
+    use rand_isaac::Isaac64Rng;
+
+    #[test]
+    fn incremental_mean() {
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        let observations: Array2<f64> =
+            Array::random_using((100, 5), Uniform::new(-100., 100.), &mut rng);
+
+        let mut rows = observations.genrows().into_iter();
+        let mut mean = IncrementalMean::new(rows.next().unwrap().to_owned());
+        for observation in rows {
+            mean.update(&observation);
+        }
+
+        assert_eq!(mean.n_observations, 100);
+        assert_abs_diff_eq!(
+            mean.current_mean,
+            observations.mean_axis(Axis(0)).unwrap(),
+            epsilon = 1e-5
+        );
+
+        let observations = observations.mapv(|x| x as f32);
+        let mut rows = observations.genrows().into_iter();
+        let mut mean = IncrementalMean::new(rows.next().unwrap().to_owned());
+        for observation in rows {
+            mean.update(&observation);
+        }
+        assert_abs_diff_eq!(
+            mean.current_mean,
+            observations.mean_axis(Axis(0)).unwrap(),
+            epsilon = 1e-3
+        );
+    }
+
+    #[test]
+    fn seeded_means_carry_no_weight() {
+        let mut mean = IncrementalMean::seeded(array![100., -100.]);
+        assert_eq!(mean.n_observations, 0);
+
+        let mut empty = IncrementalMean::seeded(array![5., 5.]);
+        empty.merge(IncrementalMean::seeded(array![7., 7.]));
+        assert_eq!(empty.current_mean, array![5., 5.]);
+        mean.merge(empty);
+        assert_eq!(mean.current_mean, array![100., -100.]);
+
+        mean.update(&array![1., 2.]);
+        assert_eq!(mean.current_mean, array![1., 2.]);
+        mean.update(&array![3., 4.]);
+        assert_eq!(mean.current_mean, array![2., 3.]);
+        assert_eq!(mean.n_observations, 2);
+    }
+
+    // Two-pass statistics, for reference
+    fn two_pass_mean_and_variance(observations: &Array2<f64>) -> (Array1<f64>, Array1<f64>) {
+        let mean = observations.sum_axis(Axis(0)) / observations.nrows() as f64;
+        let deviations = observations - &mean;
+        let variance = (&deviations * &deviations).sum_axis(Axis(0)) / observations.nrows() as f64;
+        (mean, variance)
+    }
+
+    fn running_statistics(observations: ArrayView2<f64>) -> RunningStatistics<f64> {
+        let mut rows = observations.genrows().into_iter();
+        let mut statistics = RunningStatistics::new(rows.next().unwrap().to_owned());
+        for observation in rows {
+            statistics.update(&observation);
+        }
+        statistics
+    }
+
+    #[test]
+    fn running_statistics_on_ill_conditioned_data() {
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        // A tiny spread around a huge offset: E[x²] - E[x]² would be all rounding error
+        let noise: Array2<f64> = Array::random_using((1000, 3), Uniform::new(-1., 1.), &mut rng);
+        let observations = noise + 1e9;
+        let (expected_mean, expected_variance) = two_pass_mean_and_variance(&observations);
+
+        let statistics = running_statistics(observations.view());
+
+        assert_eq!(statistics.n_observations, 1000);
+        assert_relative_eq!(statistics.mean, expected_mean, max_relative = 1e-14);
+        assert_relative_eq!(
+            statistics.variance(0.),
+            expected_variance,
+            max_relative = 1e-6
+        );
+        assert_relative_eq!(
+            statistics.variance(1.),
+            observations.var_axis(Axis(0), 1.),
+            max_relative = 1e-6
+        );
+        assert_eq!(
+            statistics.min,
+            observations.fold_axis(Axis(0), f64::INFINITY, |a, &b| a.min(b))
+        );
+        assert_eq!(
+            statistics.max,
+            observations.fold_axis(Axis(0), f64::NEG_INFINITY, |a, &b| a.max(b))
+        );
+        // The mean is updated as in `IncrementalMean`
+        let mut rows = observations.genrows().into_iter();
+        let mut mean = IncrementalMean::new(rows.next().unwrap().to_owned());
+        for observation in rows {
+            mean.update(&observation);
+        }
+        assert_eq!(statistics.mean, mean.current_mean);
+    }
+
+    #[test]
+    fn merged_running_statistics() {
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        let noise: Array2<f64> = Array::random_using((300, 2), Uniform::new(-1., 1.), &mut rng);
+        let observations = noise + 1e8;
+        let (expected_mean, expected_variance) = two_pass_mean_and_variance(&observations);
+
+        let mut statistics = running_statistics(observations.slice(s![..50, ..]));
+        statistics.merge(running_statistics(observations.slice(s![50..220, ..])));
+        statistics.merge(running_statistics(observations.slice(s![220.., ..])));
+
+        let single_pass = running_statistics(observations.view());
+        assert_eq!(statistics.n_observations, 300);
+        assert_relative_eq!(statistics.mean, expected_mean, max_relative = 1e-14);
+        assert_relative_eq!(
+            statistics.variance(0.),
+            expected_variance,
+            max_relative = 1e-6
+        );
+        assert_eq!(statistics.min, single_pass.min);
+        assert_eq!(statistics.max, single_pass.max);
+    }
+
+    #[test]
+    fn weighted_running_statistics() {
+        let observations = array![[1., 10.], [2., 20.], [4., 0.]];
+        let duplicated = array![
+            [1., 10.],
+            [2., 20.],
+            [2., 20.],
+            [2., 20.],
+            [4., 0.],
+            [4., 0.]
+        ];
+
+        let mut weighted = RunningStatistics::new(observations.row(0).to_owned());
+        weighted.update_weighted(&observations.row(1), 3.);
+        weighted.update_weighted(&observations.row(2), 2.);
+        let expected = running_statistics(duplicated.view());
+
+        assert_eq!(weighted.n_observations, 3);
+        assert_eq!(weighted.total_weight, 6.);
+        assert_abs_diff_eq!(weighted.mean, expected.mean, epsilon = 1e-12);
+        assert_abs_diff_eq!(
+            weighted.variance(1.),
+            duplicated.var_axis(Axis(0), 1.),
+            epsilon = 1e-12
+        );
+        assert_abs_diff_eq!(weighted.std(0.), expected.std(0.), epsilon = 1e-12);
+        assert_eq!(weighted.min, array![1., 0.]);
+        assert_eq!(weighted.max, array![4., 20.]);
+    }
+
+    #[test]
+    fn clusters_come_with_their_spread() {
+        let observations = array![[0., 0.], [2., 0.], [10., 10.], [10., 14.]];
+        let memberships = array![0, 0, 1, 1];
+
+        let clusters = compute_centroids_hashmap(&observations, &memberships);
+
+        assert_eq!(clusters[&0].mean, array![1., 0.]);
+        assert_eq!(clusters[&0].variance(0.), array![1., 0.]);
+        assert_eq!(clusters[&1].mean, array![10., 12.]);
+        assert_eq!(clusters[&1].std(0.), array![0., 2.]);
+    }
+
+    #[test]
+    fn merged_means() {
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        let observations: Array2<f64> =
+            Array::random_using((100, 5), Uniform::new(-100., 100.), &mut rng);
+
+        let mean_of = |rows: ArrayView2<f64>| {
+            let mut rows = rows.genrows().into_iter();
+            let mut mean = IncrementalMean::new(rows.next().unwrap().to_owned());
+            for observation in rows {
+                mean.update(&observation);
+            }
+            mean
+        };
+        let mut mean = mean_of(observations.slice(s![..30, ..]));
+        mean.merge(mean_of(observations.slice(s![30.., ..])));
+
+        assert_eq!(mean.n_observations, 100);
+        assert_abs_diff_eq!(
+            mean.current_mean,
+            observations.mean_axis(Axis(0)).unwrap(),
+            epsilon = 1e-9
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_centroids_match_the_sequential_ones() {
+        use crate::clustering::assignment::PARALLEL_CHUNK_SIZE;
+
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        let n_observations = 3 * PARALLEL_CHUNK_SIZE + 17;
+        let observations: Array2<f64> =
+            Array::random_using((n_observations, 4), Uniform::new(-100., 100.), &mut rng);
+        let memberships: Array1<usize> =
+            Array::random_using(n_observations, Uniform::new(0, 6), &mut rng);
+
+        let with_threads = |n_threads: usize| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(n_threads)
+                .build()
+                .unwrap()
+                .install(|| compute_centroids(6, &observations, &memberships))
+        };
+        let parallel = with_threads(4);
+        assert_eq!(parallel, with_threads(1));
+
+        let sequential = sequential_centroids_hashmap(&observations, &memberships, None);
+        for (cluster_index, mean) in sequential {
+            assert_abs_diff_eq!(parallel.row(cluster_index), mean.mean, epsilon = 1e-10);
+        }
+    }
+
+    #[test]
+    fn centroids_of_two_clusters() {
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        let (cluster_size, n_features) = (100, 4);
+        let cluster_1: Array2<f64> = Array::random_using(
+            (cluster_size, n_features),
+            Uniform::new(-100., 100.),
+            &mut rng,
+        );
+        let cluster_2: Array2<f64> = Array::random_using(
+            (cluster_size, n_features),
+            Uniform::new(-100., 100.),
+            &mut rng,
+        );
+        let observations = stack(Axis(0), &[cluster_1.view(), cluster_2.view()]).unwrap();
+        let memberships = stack(
+            Axis(0),
+            &[
+                Array1::<usize>::zeros(cluster_size).view(),
+                Array1::<usize>::ones(cluster_size).view(),
+            ],
+        )
+        .unwrap();
+
+        let centroids = compute_centroids(2, &observations, &memberships);
+
+        assert_eq!(centroids.dim(), (2, n_features));
+        assert_abs_diff_eq!(
+            centroids.row(0),
+            cluster_1.mean_axis(Axis(0)).unwrap(),
+            epsilon = 1e-5
+        );
+        assert_abs_diff_eq!(
+            centroids.row(1),
+            cluster_2.mean_axis(Axis(0)).unwrap(),
+            epsilon = 1e-5
+        );
+    }
+
+    #[test]
+    fn empty_clusters_are_handled_according_to_the_policy() {
+        let observations = array![[0.], [1.], [10.], [14.]];
+        let previous_centroids = array![[0.5], [12.], [100.]];
+        // Nobody is close to the third centroid
+        let memberships = array![0, 0, 1, 1];
+        let update = |policy| {
+            update_centroids(
+                &previous_centroids,
+                &observations,
+                &memberships,
+                None,
+                policy,
+                &Euclidean,
+            )
+        };
+
+        assert_eq!(
+            update(EmptyClusterPolicy::Error),
+            Err(KMeansError::EmptyCluster(2))
+        );
+        assert_eq!(
+            update(EmptyClusterPolicy::KeepPrevious).unwrap(),
+            array![[0.5], [12.], [100.]]
+        );
+        // 10. and 14. are both 2 away from their centroid, 12.: ties go to the lowest index
+        assert_eq!(
+            update(EmptyClusterPolicy::ReseedFarthest).unwrap(),
+            array![[0.5], [12.], [10.]]
+        );
+    }
+
+    #[test]
+    fn each_empty_cluster_gets_a_different_observation() {
+        let observations = array![[0.], [1.], [10.]];
+        let previous_centroids = array![[4.], [50.], [60.]];
+        let memberships = array![0, 0, 0];
+
+        let centroids = update_centroids(
+            &previous_centroids,
+            &observations,
+            &memberships,
+            None,
+            EmptyClusterPolicy::ReseedFarthest,
+            &Euclidean,
+        )
+        .unwrap();
+
+        assert_abs_diff_eq!(centroids, array![[11. / 3.], [10.], [0.]]);
+    }
+
+    #[test]
+    fn weights_are_equivalent_to_duplicated_observations() {
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        let observations: Array2<f64> =
+            Array::random_using((60, 3), Uniform::new(-10., 10.), &mut rng);
+        let memberships: Array1<usize> = Array::random_using(60, Uniform::new(0, 4), &mut rng);
+        let counts: Array1<usize> = Array::random_using(60, Uniform::new(1, 5), &mut rng);
+
+        let duplicated_indices: Vec<usize> = counts
+            .iter()
+            .enumerate()
+            .flat_map(|(index, &count)| vec![index; count])
+            .collect();
+        let duplicated_observations = observations.select(Axis(0), &duplicated_indices);
+        let duplicated_memberships = memberships.select(Axis(0), &duplicated_indices);
+        let sample_weight = counts.mapv(|count| count as f64);
+
+        fn update(
+            observations: &Array2<f64>,
+            memberships: &Array1<usize>,
+            sample_weight: Option<ArrayView1<f64>>,
+            distance: &impl Distance,
+        ) -> Array2<f64> {
+            let previous_centroids = Array2::zeros((4, 3));
+            let policy = EmptyClusterPolicy::Error;
+            update_centroids(
+                &previous_centroids,
+                observations,
+                memberships,
+                sample_weight,
+                policy,
+                distance,
+            )
+            .unwrap()
+        }
+        let weighted = update(
+            &observations,
+            &memberships,
+            Some(sample_weight.view()),
+            &Euclidean,
+        );
+        let duplicated = update(
+            &duplicated_observations,
+            &duplicated_memberships,
+            None,
+            &Euclidean,
+        );
+        assert_abs_diff_eq!(weighted, duplicated, epsilon = 1e-10);
+
+        let weighted = update(
+            &observations,
+            &memberships,
+            Some(sample_weight.view()),
+            &Manhattan,
+        );
+        let duplicated = update(
+            &duplicated_observations,
+            &duplicated_memberships,
+            None,
+            &Manhattan,
+        );
+        assert_eq!(weighted, duplicated);
+    }
+
+    #[test]
+    fn weighted_median() {
+        assert_eq!(median(vec![(3., 1.), (1., 1.), (2., 1.)]), 2.);
+        assert_eq!(median(vec![(3., 1.), (1., 1.), (2., 1.), (4., 1.)]), 2.5);
+        assert_eq!(median(vec![(3., 1.), (1., 1.), (2., 3.), (4., 1.)]), 2.);
+        assert_eq!(median(vec![(1., 2.), (10., 2.)]), 5.5);
+        assert_eq!(median(vec![(1., 0.5), (10., 2.)]), 10.);
+    }
+
+    #[test]
+    fn centres_depend_on_the_distance() {
+        let observations = array![[0., 1.], [1., 1.], [8., 1.], [3., 3.], [3., 5.]];
+        let previous_centroids = array![[0., 0.], [5., 5.]];
+        let memberships = array![0, 0, 0, 1, 1];
+        let policy = EmptyClusterPolicy::Error;
+
+        let means = update_centroids(
+            &previous_centroids,
+            &observations,
+            &memberships,
+            None,
+            policy,
+            &Euclidean,
+        );
+        assert_abs_diff_eq!(means.unwrap(), array![[3., 1.], [3., 4.]]);
+
+        let medians = update_centroids(
+            &previous_centroids,
+            &observations,
+            &memberships,
+            None,
+            policy,
+            &Manhattan,
+        );
+        assert_abs_diff_eq!(medians.unwrap(), array![[1., 1.], [3., 4.]]);
+
+        let normalised_means = update_centroids(
+            &previous_centroids,
+            &observations,
+            &memberships,
+            None,
+            policy,
+            &Cosine,
+        );
+        assert_abs_diff_eq!(
+            normalised_means.unwrap(),
+            array![[3., 1.], [3., 4.]] / array![[10_f64.sqrt()], [5.]],
+            epsilon = 1e-12
+        );
+    }
+}
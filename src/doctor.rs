@@ -0,0 +1,242 @@
+use crate::i18n::{fill, Locale, Message};
+use std::fs::{metadata, read_to_string, remove_file, OpenOptions};
+use std::path::Path;
+use std::process::Command;
+
+/// The minimum toolchain version required by the workshop, as stated in the README
+/// and in `rust-version` in `Cargo.toml`.
+///
+/// Checked by building and testing a fresh checkout with that toolchain: running the koans
+/// compiles the dev-dependencies too, and `ndarray-npy` pulls in a `pest` that needs 1.83.
+pub const MINIMUM_RUST_VERSION: (u64, u64) = (1, 83);
+
+/// The Python packages imported by the notebooks in the `python` folder.
+const NOTEBOOK_PACKAGES: [&str; 4] = ["numpy", "pandas", "matplotlib", "altair"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Ok,
+    /// Something that will get in the way of part of the workshop, e.g. the notebooks.
+    Warning,
+    /// Something that will get in the way of the whole workshop.
+    Problem,
+}
+
+/// The outcome of a single `koans doctor` check, with an actionable `fix` if something is off.
+#[derive(Debug, Clone)]
+pub struct Diagnosis {
+    pub severity: Severity,
+    pub summary: String,
+    pub fix: Option<String>,
+}
+
+impl Diagnosis {
+    fn ok(summary: String) -> Self {
+        Self {
+            severity: Severity::Ok,
+            summary,
+            fix: None,
+        }
+    }
+
+    fn warning(summary: String, fix: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            summary,
+            fix: Some(fix),
+        }
+    }
+
+    fn problem(summary: String, fix: String) -> Self {
+        Self {
+            severity: Severity::Problem,
+            summary,
+            fix: Some(fix),
+        }
+    }
+}
+
+/// Run all preflight checks, to be done before the workshop (when the Wi-Fi still works!).
+pub fn diagnose(progress_path: impl AsRef<Path>, locale: Locale) -> Vec<Diagnosis> {
+    vec![
+        check_rust_version(locale),
+        check_dependencies(locale),
+        check_python(locale),
+        check_progress_file(progress_path, locale),
+    ]
+}
+
+pub fn check_rust_version(locale: Locale) -> Diagnosis {
+    let minimum = format!("{}.{}", MINIMUM_RUST_VERSION.0, MINIMUM_RUST_VERSION.1);
+    let version = rustc_verbose_version().and_then(|output| {
+        let release = field(&output, "release")?;
+        let mut numbers = release.split(|c: char| !c.is_ascii_digit());
+        let major = numbers.next()?.parse::<u64>().ok()?;
+        let minor = numbers.next()?.parse::<u64>().ok()?;
+        Some((release, (major, minor)))
+    });
+
+    match version {
+        None => Diagnosis::problem(
+            locale.message(Message::DoctorRustMissing).into(),
+            locale.message(Message::DoctorRustInstall).into(),
+        ),
+        Some((release, version)) if version < MINIMUM_RUST_VERSION => Diagnosis::problem(
+            fill(
+                locale.message(Message::DoctorRustTooOld),
+                &[&release, &minimum],
+            ),
+            locale.message(Message::DoctorRustUpdate).into(),
+        ),
+        Some((release, _)) => Diagnosis::ok(fill(
+            locale.message(Message::DoctorRustOk),
+            &[&release, &minimum],
+        )),
+    }
+}
+
+/// Are all the crates we depend on available without a network connection?
+pub fn check_dependencies(locale: Locale) -> Diagnosis {
+    let is_vendored = Path::new("vendor").is_dir()
+        && [".cargo/config.toml", ".cargo/config"]
+            .iter()
+            .filter_map(|path| read_to_string(path).ok())
+            .any(|config| config.contains("vendored-sources"));
+    if is_vendored {
+        return Diagnosis::ok(locale.message(Message::DoctorDepsVendored).into());
+    }
+
+    let fix = locale.message(Message::DoctorDepsFetch).to_string();
+    if !Path::new("Cargo.lock").exists() {
+        return Diagnosis::problem(locale.message(Message::DoctorNoLockfile).into(), fix);
+    }
+
+    let mut fetch = Command::new("cargo");
+    fetch.args(["fetch", "--offline"]);
+    // Only the dependencies for the current platform matter.
+    if let Some(host) = rustc_verbose_version().and_then(|output| field(&output, "host")) {
+        fetch.args(["--target", &host]);
+    }
+    match fetch.output() {
+        Ok(output) if output.status.success() => {
+            Diagnosis::ok(locale.message(Message::DoctorDepsFetched).into())
+        }
+        _ => Diagnosis::problem(locale.message(Message::DoctorDepsMissing).into(), fix),
+    }
+}
+
+/// Can we run the Jupyter notebooks in the `python` folder?
+pub fn check_python(locale: Locale) -> Diagnosis {
+    // Print the packages that cannot be imported, space-separated.
+    let script = "import importlib.util, sys; \
+                  print(' '.join(m for m in sys.argv[1:] if importlib.util.find_spec(m) is None))";
+    let fix = locale.message(Message::DoctorPythonFix).to_string();
+
+    for interpreter in &["python3", "python"] {
+        let output = Command::new(interpreter)
+            .args(["-c", script])
+            .args(NOTEBOOK_PACKAGES.iter())
+            .output();
+        if let Ok(output) = output {
+            if !output.status.success() {
+                continue;
+            }
+            let missing = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            return if missing.is_empty() {
+                Diagnosis::ok(fill(
+                    locale.message(Message::DoctorPythonOk),
+                    &[interpreter],
+                ))
+            } else {
+                Diagnosis::warning(
+                    fill(
+                        locale.message(Message::DoctorPythonMissingPackages),
+                        &[interpreter, &missing.replace(' ', ", ")],
+                    ),
+                    fix,
+                )
+            };
+        }
+    }
+    Diagnosis::warning(locale.message(Message::DoctorPythonMissing).into(), fix)
+}
+
+/// The runner records your progress in `progress_path`: it has to be writable.
+///
+/// The runner creates it on the first `cargo run`: if it does not exist yet, we check that it can
+/// be created.
+pub fn check_progress_file(progress_path: impl AsRef<Path>, locale: Locale) -> Diagnosis {
+    let path = progress_path.as_ref();
+    let displayed = path.display();
+    let exists = metadata(path).is_ok();
+    let writable = if exists {
+        OpenOptions::new().append(true).open(path).map(|_| ())
+    } else {
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .and_then(|_| remove_file(path))
+    };
+    match writable {
+        Ok(()) if exists => Diagnosis::ok(fill(
+            locale.message(Message::DoctorProgressWritable),
+            &[&displayed],
+        )),
+        Ok(()) => Diagnosis::ok(fill(
+            locale.message(Message::DoctorProgressNotCreatedYet),
+            &[&displayed],
+        )),
+        Err(e) => Diagnosis::problem(
+            fill(
+                locale.message(Message::DoctorProgressNotWritable),
+                &[&displayed, &e],
+            ),
+            fill(locale.message(Message::DoctorProgressFix), &[&displayed]),
+        ),
+    }
+}
+
+fn rustc_verbose_version() -> Option<String> {
+    let output = Command::new("rustc").arg("-vV").output().ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        None
+    }
+}
+
+// Extract `value` from a `name: value` line in the output of `rustc -vV`.
+fn field(output: &str, name: &str) -> Option<String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once(": "))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rustc_fields_are_extracted() {
+        let output = "rustc 1.38.0 (625451e37 2019-09-23)\n\
+                      binary: rustc\n\
+                      host: x86_64-unknown-linux-gnu\n\
+                      release: 1.38.0\n";
+        assert_eq!(field(output, "release"), Some("1.38.0".into()));
+        assert_eq!(
+            field(output, "host"),
+            Some("x86_64-unknown-linux-gnu".into())
+        );
+        assert_eq!(field(output, "commit-hash"), None);
+    }
+
+    #[test]
+    fn a_progress_file_that_cannot_be_created_is_a_problem() {
+        let diagnosis = check_progress_file("does/not/exist.rs", Locale::English);
+        assert_eq!(diagnosis.severity, Severity::Problem);
+        assert!(diagnosis.fix.is_some());
+    }
+}
@@ -0,0 +1,309 @@
+use std::env;
+use std::fmt::Display;
+use std::fs::read_to_string;
+use std::path::Path;
+
+/// The languages the runner can speak.
+///
+/// Adding a new locale boils down to adding a variant here, recognising its tag in
+/// `Locale::from_tag` and providing a catalogue function next to `italian`:
+/// the compiler makes sure it translates every message.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Locale {
+    English,
+    Italian,
+}
+
+impl Locale {
+    /// Parse a language tag: `it`, `it-IT`, `it_IT.UTF-8`, etc.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let language = tag.split(['_', '-', '.']).next()?.to_lowercase();
+        match language.as_str() {
+            "en" | "c" | "posix" => Some(Locale::English),
+            "it" => Some(Locale::Italian),
+            _ => None,
+        }
+    }
+
+    /// Pick a locale based on `--lang`, if provided, or on the usual environment variables
+    /// (`LC_ALL`, `LC_MESSAGES`, `LANG`). Falls back to English.
+    pub fn detect(lang_flag: Option<&str>) -> Self {
+        if let Some(locale) = lang_flag.and_then(Locale::from_tag) {
+            return locale;
+        }
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Locale::from_tag(&value))
+            .unwrap_or(Locale::English)
+    }
+
+    /// The tag used to look up translated koan prose, e.g. `translations/it/...`.
+    pub fn code(self) -> &'static str {
+        match self {
+            Locale::English => "en",
+            Locale::Italian => "it",
+        }
+    }
+
+    pub fn message(self, message: Message) -> &'static str {
+        match self {
+            Locale::English => english(message),
+            Locale::Italian => italian(message),
+        }
+    }
+
+    /// Translated prose for the koan with the given `dd_chapter/dd_name` id, read from a
+    /// sidecar file in `translations_dir` (e.g. `translations/it/00_greetings/00_greetings.md`).
+    ///
+    /// The koan doc comments are the English version: there are no sidecar files for it.
+    pub fn koan_prose(self, translations_dir: impl AsRef<Path>, koan_id: &str) -> Option<String> {
+        if self == Locale::English {
+            return None;
+        }
+        let path = translations_dir
+            .as_ref()
+            .join(self.code())
+            .join(format!("{}.md", koan_id));
+        read_to_string(path).ok()
+    }
+}
+
+/// Every message printed by the runner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Message {
+    PathNotFinished,
+    PathFinished,
+    Meditate,
+    AheadOfYouLies,
+    NoMoreTasks,
+    ReportExportedTo,
+    OpenYourFirstKoan,
+    LookForTheBlanks,
+    Skipping,
+    /// Template: number of learners, number of learners who completed the path.
+    LearnersSummary,
+    MostLearnersStuckOn,
+    Learners,
+    Usage,
+    ReportTitleCompleted,
+    ReportTitleInProgress,
+    TotalTime,
+    Attempts,
+    Failures,
+    Hints,
+    HintsUsed,
+    Koan,
+    Koans,
+    Solved,
+    Stuck,
+    TimeToSolve,
+    AverageTime,
+    HardestKoans,
+    /// Template: rustc version, minimum version.
+    DoctorRustOk,
+    DoctorRustMissing,
+    DoctorRustInstall,
+    /// Template: rustc version, minimum version.
+    DoctorRustTooOld,
+    DoctorRustUpdate,
+    DoctorDepsVendored,
+    DoctorDepsFetched,
+    DoctorNoLockfile,
+    DoctorDepsMissing,
+    DoctorDepsFetch,
+    /// Template: Python interpreter.
+    DoctorPythonOk,
+    /// Template: Python interpreter, missing packages.
+    DoctorPythonMissingPackages,
+    DoctorPythonMissing,
+    DoctorPythonFix,
+    /// Template: progress file.
+    DoctorProgressNotCreatedYet,
+    /// Template: progress file.
+    DoctorProgressWritable,
+    /// Template: progress file, error.
+    DoctorProgressNotWritable,
+    /// Template: progress file.
+    DoctorProgressFix,
+    DoctorAllGood,
+    /// Template: number of problems.
+    DoctorProblemsFound,
+}
+
+/// Replace each `{}` in `template` with the next argument in `args`.
+pub fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut args = args.iter();
+    let mut pieces = template.split("{}");
+    let mut filled = String::from(pieces.next().unwrap_or(""));
+    for piece in pieces {
+        if let Some(arg) = args.next() {
+            filled.push_str(&arg.to_string());
+        }
+        filled.push_str(piece);
+    }
+    filled
+}
+
+fn english(message: Message) -> &'static str {
+    use Message::*;
+    match message {
+        PathNotFinished => "Eternity lies ahead of us, and behind. Your path is not yet finished. 🍂",
+        PathFinished => "What is the sound of one hand clapping (for you)? 🌟",
+        Meditate => "Meditate on your approach and return. Mountains are merely mountains.",
+        AheadOfYouLies => "Ahead of you lies",
+        NoMoreTasks => "There will be no more tasks.",
+        ReportExportedTo => "Report exported to",
+        OpenYourFirstKoan => "Run `cargo run` to open your first koan.",
+        LookForTheBlanks => "Look for the blanks in",
+        Skipping => "Skipping",
+        LearnersSummary => "{} learners, {} of them reached the end of the path.",
+        MostLearnersStuckOn => "Most learners are stuck on",
+        Learners => "learners",
+        Usage => {
+            "Usage:\n\
+             \t  cargo run                                   walk the path\n\
+             \t  cargo run -- hint                           show the blanks left in your koan\n\
+             \t  cargo run -- report [--export <file>]       print or export (.md, .html, .json) your session report\n\
+             \t  cargo run -- aggregate <dir>                merge the .json progress files of a workshop room\n\
+             \t  cargo run -- doctor                         check that you are ready for the workshop\n\
+             \n\
+             \tAdd `--lang <code>` (e.g. `--lang it`) to override the language detected from `LANG`."
+        }
+        ReportTitleCompleted => "An ML introduction to ndarray - certificate of completion",
+        ReportTitleInProgress => "An ML introduction to ndarray - progress report",
+        TotalTime => "Total time",
+        Attempts => "Attempts",
+        Failures => "Failures",
+        Hints => "Hints",
+        HintsUsed => "Hints used",
+        Koan => "Koan",
+        Koans => "Koans",
+        Solved => "Solved",
+        Stuck => "Stuck",
+        TimeToSolve => "Time to solve",
+        AverageTime => "Average time",
+        HardestKoans => "Hardest koans",
+        DoctorRustOk => "rustc {} (the workshop requires {} or higher).",
+        DoctorRustMissing => "Could not run `rustc`.",
+        DoctorRustInstall => "Install Rust following https://www.rust-lang.org/tools/install",
+        DoctorRustTooOld => "rustc {} is older than {}, the minimum required version.",
+        DoctorRustUpdate => "Run `rustup update` to update your toolchain.",
+        DoctorDepsVendored => "Dependencies are vendored in `vendor`.",
+        DoctorDepsFetched => "All dependencies are available offline.",
+        DoctorNoLockfile => "There is no `Cargo.lock`: dependencies have not been fetched yet.",
+        DoctorDepsMissing => "Some dependencies are not available offline.",
+        DoctorDepsFetch => {
+            "Run `cargo fetch` while you are online. \
+             To share an offline copy, run `cargo vendor` and follow its instructions."
+        }
+        DoctorPythonOk => "{} can run the notebooks in `python`.",
+        DoctorPythonMissingPackages => "{} is missing some packages needed by the notebooks: {}.",
+        DoctorPythonMissing => "Could not find a Python interpreter to run the notebooks in `python`.",
+        DoctorPythonFix => {
+            "Run `pip install -r python/requirements.txt` or use Docker (see `python/README.md`)."
+        }
+        DoctorProgressNotCreatedYet => "`{}` will be created by `cargo run`.",
+        DoctorProgressWritable => "`{}` is writable.",
+        DoctorProgressNotWritable => "`{}` is not writable: {}.",
+        DoctorProgressFix => "Check the permissions of `{}` and of its folder.",
+        DoctorAllGood => "Everything looks good: you are ready to go!",
+        DoctorProblemsFound => "Problems found: {}. Fix them before the workshop starts.",
+    }
+}
+
+fn italian(message: Message) -> &'static str {
+    use Message::*;
+    match message {
+        PathNotFinished => "L'eternità ci sta davanti, e alle spalle. Il tuo cammino non è ancora finito. 🍂",
+        PathFinished => "Qual è il suono di una mano sola che applaude (per te)? 🌟",
+        Meditate => "Medita sul tuo approccio e ritorna. Le montagne sono solo montagne.",
+        AheadOfYouLies => "Davanti a te si trova",
+        NoMoreTasks => "Non ci saranno altri compiti.",
+        ReportExportedTo => "Resoconto esportato in",
+        OpenYourFirstKoan => "Esegui `cargo run` per aprire il tuo primo koan.",
+        LookForTheBlanks => "Cerca gli spazi vuoti in",
+        Skipping => "Ignoro",
+        LearnersSummary => "{} partecipanti, {} dei quali hanno raggiunto la fine del cammino.",
+        MostLearnersStuckOn => "La maggior parte dei partecipanti è bloccata su",
+        Learners => "partecipanti",
+        Usage => {
+            "Utilizzo:\n\
+             \t  cargo run                                   percorri il cammino\n\
+             \t  cargo run -- hint                           mostra gli spazi vuoti rimasti nel tuo koan\n\
+             \t  cargo run -- report [--export <file>]       stampa o esporta (.md, .html, .json) il resoconto della sessione\n\
+             \t  cargo run -- aggregate <dir>                unisci i file .json di avanzamento di un'aula\n\
+             \t  cargo run -- doctor                         verifica di essere pronto per il workshop\n\
+             \n\
+             \tAggiungi `--lang <codice>` (es. `--lang en`) per scegliere una lingua diversa da quella di `LANG`."
+        }
+        ReportTitleCompleted => "Un'introduzione al ML con ndarray - attestato di completamento",
+        ReportTitleInProgress => "Un'introduzione al ML con ndarray - resoconto dei progressi",
+        TotalTime => "Tempo totale",
+        Attempts => "Tentativi",
+        Failures => "Fallimenti",
+        Hints => "Suggerimenti",
+        HintsUsed => "Suggerimenti usati",
+        Koan => "Koan",
+        Koans => "Koan",
+        Solved => "Risolto",
+        Stuck => "Bloccati",
+        TimeToSolve => "Tempo di risoluzione",
+        AverageTime => "Tempo medio",
+        HardestKoans => "Koan più difficili",
+        DoctorRustOk => "rustc {} (il workshop richiede la {} o successive).",
+        DoctorRustMissing => "Impossibile eseguire `rustc`.",
+        DoctorRustInstall => "Installa Rust seguendo https://www.rust-lang.org/tools/install",
+        DoctorRustTooOld => "rustc {} è precedente alla {}, la versione minima richiesta.",
+        DoctorRustUpdate => "Esegui `rustup update` per aggiornare la tua toolchain.",
+        DoctorDepsVendored => "Le dipendenze sono copiate in `vendor`.",
+        DoctorDepsFetched => "Tutte le dipendenze sono disponibili offline.",
+        DoctorNoLockfile => "Non c'è un `Cargo.lock`: le dipendenze non sono ancora state scaricate.",
+        DoctorDepsMissing => "Alcune dipendenze non sono disponibili offline.",
+        DoctorDepsFetch => {
+            "Esegui `cargo fetch` mentre sei online. \
+             Per condividere una copia offline, esegui `cargo vendor` e segui le sue istruzioni."
+        }
+        DoctorPythonOk => "{} può eseguire i notebook in `python`.",
+        DoctorPythonMissingPackages => "A {} mancano alcuni pacchetti richiesti dai notebook: {}.",
+        DoctorPythonMissing => "Nessun interprete Python trovato per eseguire i notebook in `python`.",
+        DoctorPythonFix => {
+            "Esegui `pip install -r python/requirements.txt` oppure usa Docker (vedi `python/README.md`)."
+        }
+        DoctorProgressNotCreatedYet => "`{}` verrà creato da `cargo run`.",
+        DoctorProgressWritable => "`{}` è scrivibile.",
+        DoctorProgressNotWritable => "`{}` non è scrivibile: {}.",
+        DoctorProgressFix => "Controlla i permessi di `{}` e della sua cartella.",
+        DoctorAllGood => "Tutto a posto: sei pronto per partire!",
+        DoctorProblemsFound => "Problemi trovati: {}. Risolvili prima dell'inizio del workshop.",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn language_tags_are_parsed_leniently() {
+        assert_eq!(Locale::from_tag("it_IT.UTF-8"), Some(Locale::Italian));
+        assert_eq!(Locale::from_tag("it-IT"), Some(Locale::Italian));
+        assert_eq!(Locale::from_tag("EN"), Some(Locale::English));
+        assert_eq!(Locale::from_tag("C.UTF-8"), Some(Locale::English));
+        assert_eq!(Locale::from_tag("tlh"), None);
+    }
+
+    #[test]
+    fn the_lang_flag_wins_over_the_environment() {
+        assert_eq!(Locale::detect(Some("it")), Locale::Italian);
+    }
+
+    #[test]
+    fn templates_are_filled_in_order() {
+        assert_eq!(
+            fill(english(Message::LearnersSummary), &[&3, &1]),
+            "3 learners, 1 of them reached the end of the path."
+        );
+        assert_eq!(fill("no placeholders", &[&3]), "no placeholders");
+    }
+}
@@ -12,6 +12,10 @@ mod initialisation_input {
     // When implementing the standard K-means algorithm, the most common initialisation
     // technique is the Forgy method: as your first set of centroids just pick `n_clusters`
     // distinct observations from your dataset - as simple as that (and it works quite well!).
+    //
+    // Picking distinct indices at random is a job for `rand::seq::index::sample`... if it weren't
+    // for a bug in the `Isaac64Rng` we use, which makes it crash in debug builds.
+    // Use the `sample_indices` helper below instead.
     pub fn get_random_centroids(
         n_clusters: usize,
         observations: __,
@@ -20,6 +24,18 @@ mod initialisation_input {
         __
     }
 
+    // Helper function.
+    // Pick `amount` distinct indices in `0..length`, in random order, with a partial Fisher-Yates
+    // shuffle: after the i-th swap, the first i indices are a random sample of all of them.
+    pub fn sample_indices(length: usize, amount: usize, rng: &mut impl Rng) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..length).collect();
+        for i in 0..amount {
+            indices.swap(i, rng.gen_range(i, length));
+        }
+        indices.truncate(amount);
+        indices
+    }
+
     // Helper function.
     // Check if there is at least one row in `matrix` that is equal to `row`
     fn is_row_of(matrix: &Array2<f64>, row: &ArrayView1<f64>) -> bool {
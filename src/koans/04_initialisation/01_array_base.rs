@@ -1,11 +1,12 @@
 #[cfg(test)]
 mod initialisation_array_base {
     use ndarray::{array, Array, Array2, ArrayBase, ArrayView1, Axis, Data, DataMut, Ix1, Ix2};
-    use ndarray_rand::rand;
     use ndarray_rand::rand::{Rng, SeedableRng};
     use ndarray_rand::rand_distr::StandardNormal;
     use ndarray_rand::RandomExt;
     use rand_isaac::Isaac64Rng;
+    // Our helper to pick distinct indices at random, from the previous koan
+    use super::initialisation_input::sample_indices;
 
     /// [!!! Deep-dive warning - brace yourselves !!!]
     ///
@@ -76,7 +77,7 @@ mod initialisation_array_base {
         S: Data<Elem = f64>,
     {
         let (n_samples, _) = observations.dim();
-        let indices = rand::seq::index::sample(rng, n_samples, n_clusters).into_vec();
+        let indices = sample_indices(n_samples, n_clusters, rng);
         observations.select(Axis(0), &indices)
     }
 
@@ -0,0 +1,83 @@
+#[cfg(test)]
+mod initialisation_k_means_plus_plus {
+    use ndarray::{array, stack, Array, Array1, Array2, ArrayBase, Axis, Data, Ix2};
+    use ndarray_rand::rand::distributions::{Distribution, WeightedIndex};
+    use ndarray_rand::rand::{Rng, SeedableRng};
+    use ndarray_rand::rand_distr::StandardNormal;
+    use ndarray_rand::RandomExt;
+    use ndarray_stats::DeviationExt;
+    use rand_isaac::Isaac64Rng;
+
+    /// The Forgy method is as simple as it gets, but it has a weakness: nothing stops it
+    /// from picking two initial centroids from the same cluster. When that happens K-means
+    /// often gets stuck in a poor local minimum - two centroids sharing a cluster,
+    /// one centroid straddling two.
+    ///
+    /// k-means++ spreads the initial centroids out:
+    /// - the first centroid is an observation picked uniformly at random;
+    /// - each of the following ones is an observation picked with a probability proportional
+    ///   to its squared distance from the closest centroid chosen so far.
+    ///
+    /// Observations that are far away from all current centroids are very likely to be chosen,
+    /// observations that coincide with one of them are never chosen.
+    ///
+    /// `rand` can do the heavy lifting of weighted sampling for us: `WeightedIndex::new`
+    /// takes an iterator of weights and returns a distribution over their indexes.
+    /// `distribution.sample(rng)` returns index `i` with probability `weights[i] / sum(weights)`.
+    ///
+    /// Two more helpers you might find useful:
+    /// - `rng.gen_range(low, high)` returns an integer uniformly sampled in `[low, high)`;
+    /// - `a.sq_l2_dist(&b)`, from `ndarray-stats`' `DeviationExt`, returns the squared
+    ///   euclidean distance between `a` and `b` (wrapped in a `Result`: they might have
+    ///   different shapes!).
+    pub fn k_means_plus_plus(
+        n_clusters: usize,
+        observations: &ArrayBase<impl Data<Elem = f64>, Ix2>,
+        rng: &mut impl Rng,
+    ) -> Array2<f64> {
+        let (n_samples, _) = observations.dim();
+        let mut indices = vec![__];
+        // The squared distance of each observation from its closest centroid
+        let mut weights: Array1<f64> = Array1::from_elem(n_samples, f64::INFINITY);
+
+        while indices.len() < n_clusters {
+            let last_centroid = observations.row(*indices.last().unwrap());
+            for (weight, observation) in weights.iter_mut().zip(observations.genrows()) {
+                *weight = __;
+            }
+            let distribution = WeightedIndex::new(__).unwrap();
+            indices.push(__);
+        }
+        observations.select(Axis(0), &indices)
+    }
+
+    #[test]
+    fn far_apart_clusters() {
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        let n_features = 2;
+        // Two tight clusters, far away from each other
+        let left: Array2<f64> = Array::random_using((50, n_features), StandardNormal, &mut rng);
+        let right = Array::random_using((50, n_features), StandardNormal, &mut rng) + array![1000., 0.];
+        let observations = stack(Axis(0), &[left.view(), right.view()]).unwrap();
+
+        let centroids = k_means_plus_plus(2, &observations, &mut rng);
+
+        // One centroid on each side: a Forgy initialisation gets it wrong half the time!
+        let n_on_the_right = centroids.column(0).iter().filter(|&&x| x > 500.).count();
+        assert_eq!(n_on_the_right, 1);
+    }
+
+    #[test]
+    fn already_picked_observations_are_never_picked_again() {
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        let observations = array![[0., 0.], [1., 0.], [0., 1.]];
+
+        let centroids = k_means_plus_plus(3, &observations, &mut rng);
+
+        // Each observation is picked exactly once
+        for observation in observations.genrows() {
+            let n_picked = centroids.genrows().into_iter().filter(|c| c == &observation).count();
+            assert_eq!(n_picked, 1);
+        }
+    }
+}
@@ -3,8 +3,10 @@ mod update_centroids_array2 {
     use crate::path_to_enlightenment::update_centroids_hashmap::compute_centroids_hashmap;
     use approx::assert_abs_diff_eq;
     use ndarray::{array, s, stack, Array, Array1, Array2, ArrayBase, Axis, Data, Ix1, Ix2};
+    use ndarray_rand::rand::{Rng, SeedableRng};
     use ndarray_rand::rand_distr::Uniform;
     use ndarray_rand::RandomExt;
+    use rand_isaac::Isaac64Rng;
     use std::collections::HashMap;
 
     /// As we highlighted several times, K-means is an iterative algorithm.
@@ -68,4 +70,30 @@ mod update_centroids_array2 {
 
         assert_eq!(centroids.len_of(Axis(0)), 2);
     }
+
+    #[test]
+    fn centroids_array2_interleaved() {
+        // Real datasets are not sorted by cluster: observations belonging to different
+        // clusters are interleaved, as in this synthetic dataset where each observation
+        // is assigned to one of three clusters at random.
+        let mut rng = Isaac64Rng::seed_from_u64(42);
+        let n_observations = 1000;
+        let observations: Array2<f64> =
+            Array::random_using((n_observations, 2), Uniform::new(-100., 100.), &mut rng);
+        let memberships: Array1<usize> = (0..n_observations).map(|_| rng.gen_range(0, 3)).collect();
+
+        let computed_centroids = compute_centroids(3, &observations, &memberships);
+
+        for (cluster_index, centroid) in computed_centroids.genrows().into_iter().enumerate() {
+            // The positions of the observations belonging to the cluster
+            let indexes: Vec<usize> = (0..memberships.len())
+                .filter(|&index| memberships[index] == cluster_index)
+                .collect();
+            let expected_centroid = observations
+                .select(Axis(0), &indexes)
+                .mean_axis(Axis(0))
+                .unwrap();
+            assert_abs_diff_eq!(centroid, expected_centroid, epsilon = 1e-5);
+        }
+    }
 }
@@ -14,9 +14,12 @@ mod k_means_assembling {
     use rand_isaac::Isaac64Rng;
     // All the routines we worked hard to implement!
     use super::cluster_generation_dataset::generate_dataset;
-    use super::initialisation_array_base::get_random_centroids;
+    use super::initialisation_k_means_plus_plus::k_means_plus_plus;
     use super::assignment_cluster_memberships::compute_cluster_memberships;
     use super::update_centroids_array2::compute_centroids;
+    // Cluster indices are arbitrary: we need to match our centroids with the expected ones
+    // before comparing them. See `koans::clustering::alignment` if you are curious!
+    use koans::clustering::alignment::align_centroids;
 
     /// In the end, here we are!
     /// We just need to glue together everything we have developed so far to get a working
@@ -38,7 +41,7 @@ mod k_means_assembling {
         tolerance: f64,
         max_n_iterations: usize,
     ) -> Array2<f64> {
-        let mut centroids = get_random_centroids(n_clusters, observations, rng);
+        let mut centroids = k_means_plus_plus(n_clusters, observations, rng);
 
         let mut has_converged = false;
         let mut n_iterations = 0;
@@ -59,6 +62,20 @@ mod k_means_assembling {
         centroids
     }
 
+    /// The within-cluster sum of squares: the sum of the squared distances between each observation
+    /// and its closest centroid. The lower, the better `centroids` fit `observations`.
+    fn inertia(centroids: &Array2<f64>, observations: &Array2<f64>) -> f64 {
+        let memberships = compute_cluster_memberships(centroids, observations);
+        observations
+            .genrows()
+            .into_iter()
+            .zip(memberships.iter())
+            .map(|(observation, &cluster_index)| {
+                observation.sq_l2_dist(&centroids.row(cluster_index)).unwrap()
+            })
+            .sum()
+    }
+
     #[test]
     fn k_means_test_drive() {
         let expected_centroids = array![[10., 10.], [1., 12.], [20., 30.], [-20., 30.],];
@@ -71,9 +88,25 @@ mod k_means_assembling {
 
         let dataset = generate_dataset(n, expected_centroids.view(), &mut rng);
 
-        let centroids = k_means(n_clusters, &dataset, &mut rng, tolerance, max_n_iterations);
+        // K-means only finds a local minimum: even starting from k-means++, we might be unlucky
+        // and end up with two centroids sharing a cluster. Let's run it a few times
+        // and keep the centroids with the lowest inertia.
+        let n_runs = 10;
+        let centroids = (0..n_runs)
+            .map(|_| k_means(n_clusters, &dataset, &mut rng, tolerance, max_n_iterations))
+            .min_by(|a, b| {
+                inertia(a, &dataset)
+                    .partial_cmp(&inertia(b, &dataset))
+                    .unwrap()
+            })
+            .unwrap();
         let cluster_memberships = compute_cluster_memberships(&centroids, &dataset);
 
+        // Each cluster has 1000 observations with unit variance: its mean should be within
+        // a few 1/sqrt(1000) ≈ 0.03 of the centroid it was generated around
+        let aligned_centroids = align_centroids(&centroids, &expected_centroids);
+        assert_abs_diff_eq!(aligned_centroids, expected_centroids, epsilon = 0.2);
+
         write_npy("python/clustered_dataset.npy", dataset)
             .expect("Failed to write .npy file");
         write_npy("python/clustered_memberships.npy", cluster_memberships.map(|&x| x as u64))
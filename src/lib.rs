@@ -2,10 +2,22 @@
 #![allow(unused_imports)]
 #![allow(unused_macros)]
 
+// Link the BLAS implementation used by `ndarray` for matrix products.
+#[cfg(feature = "blas")]
+extern crate blas_src;
+
+pub mod aggregate;
+pub mod clustering;
+pub mod doctor;
+pub mod i18n;
+pub mod report;
+pub mod session;
+
 use regex::Regex;
 use std::ffi::OsString;
 use std::fs::{read_dir, FileType, OpenOptions};
-use std::io::{BufRead, BufReader, Write};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::PathBuf;
 
 pub struct KoanCollection {
     path: String,
@@ -17,7 +29,7 @@ impl KoanCollection {
     pub fn new(path: &str, enlightenment_path: &str) -> Self {
         let mut koans: Vec<(OsString, OsString)> = read_dir(path)
             .unwrap()
-            .map(|f| {
+            .flat_map(|f| {
                 let entry = f.unwrap();
                 // Each entry in path has to be a directory!
                 assert!(
@@ -30,7 +42,6 @@ impl KoanCollection {
                     .unwrap()
                     .map(move |f| (directory_name.to_owned(), f.unwrap().file_name()))
             })
-            .flatten()
             .collect();
         // Sort them in lexicographical order - koans are prefixed with `dd_`
         koans.sort();
@@ -42,33 +53,75 @@ impl KoanCollection {
         }
     }
 
-    pub fn n_opened(&self) -> usize {
-        let file = OpenOptions::new()
-            .read(true)
+    /// Create an empty progress file, if there is none yet.
+    ///
+    /// The runner is the only one in charge of the progress file: the build never creates it,
+    /// to avoid IDEs running `cargo check` in the background leaving the path in a confusing state.
+    pub fn initialise(&self) {
+        OpenOptions::new()
+            .create(true)
             .append(true)
             .open(&self.enlightenment_path)
-            .unwrap();
-        BufReader::new(&file).lines().count()
+            .unwrap_or_else(|e| {
+                panic!(
+                    "Failed to create the progress file {}: {}",
+                    self.enlightenment_path, e
+                )
+            });
+    }
+
+    /// The ids of the koans listed in the progress file, in the order they were opened.
+    fn opened_ids(&self) -> Vec<String> {
+        match OpenOptions::new().read(true).open(&self.enlightenment_path) {
+            Ok(file) => BufReader::new(&file)
+                .lines()
+                .filter_map(|line| {
+                    let line = line.ok()?;
+                    let id = line.trim().strip_prefix("include!(\"koans/")?;
+                    Some(id.strip_suffix(".rs\");")?.to_string())
+                })
+                .collect(),
+            // No progress file, no koan opened so far
+            Err(ref e) if e.kind() == ErrorKind::NotFound => vec![],
+            Err(e) => panic!(
+                "Failed to read the progress file {}: {}",
+                self.enlightenment_path, e
+            ),
+        }
+    }
+
+    pub fn n_opened(&self) -> usize {
+        self.opened().count()
     }
 
+    /// The koans opened so far, in the order they were opened.
+    ///
+    /// Koans are matched by name rather than by position: adding a koan in the middle of the path
+    /// does not shift the progress of learners who have already gone past it.
     pub fn opened(&self) -> impl Iterator<Item = &Koan> {
-        self.koans.iter().take(self.n_opened())
+        self.opened_ids()
+            .into_iter()
+            .filter_map(move |id| self.koans.iter().find(|koan| String::from(*koan) == id))
     }
 
+    /// The first koan along the path that has not been opened yet.
     pub fn next(&self) -> Option<&Koan> {
-        let n_opened = self.n_opened();
-        if n_opened == self.koans.len() {
-            None
-        } else {
-            Some(&self.koans[n_opened])
-        }
+        let opened_ids = self.opened_ids();
+        self.koans
+            .iter()
+            .find(|koan| !opened_ids.contains(&String::from(*koan)))
+    }
+
+    /// The location of the source file of `koan`.
+    pub fn source_path(&self, koan: &Koan) -> PathBuf {
+        let koan_filename: String = koan.into();
+        PathBuf::from(&self.path).join(format!("{}.rs", koan_filename))
     }
 
-    pub fn open_next(&mut self) -> Result<&Koan, ()> {
+    pub fn open_next(&mut self) -> Option<&Koan> {
+        self.initialise();
         let mut file = OpenOptions::new()
-            .read(true)
             .append(true)
-            .write(true)
             .open(&self.enlightenment_path)
             .unwrap();
 
@@ -76,10 +129,8 @@ impl KoanCollection {
         if let Some(koan) = koan {
             let koan_filename: String = koan.into();
             writeln!(file, "include!(\"koans/{:}.rs\");", koan_filename).unwrap();
-            Ok(koan)
-        } else {
-            Err(())
         }
+        koan
     }
 }
 
@@ -125,11 +176,50 @@ impl From<(OsString, OsString)> for Koan {
     }
 }
 
-impl Into<String> for &Koan {
-    fn into(self) -> String {
+impl From<&Koan> for String {
+    fn from(koan: &Koan) -> String {
         format!(
             "{:02}_{}/{:02}_{}",
-            &self.parent_number, &self.parent_name, &self.number, &self.name
+            &koan.parent_number, &koan.parent_name, &koan.number, &koan.name
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+    use std::fs::{remove_file, write};
+
+    #[test]
+    fn koans_added_to_the_path_do_not_shift_progress() {
+        let progress_path = temp_dir().join("koans_progress_inserted_koan.rs");
+        let progress_path = progress_path.to_str().unwrap();
+        let inserted = "04_initialisation/02_k_means_plus_plus";
+        let reached = "05_assignment/00_distance";
+        // A learner who reached chapter 5 before `inserted` was added to the path
+        let ids: Vec<String> = KoanCollection::new("src/koans", progress_path)
+            .koans
+            .iter()
+            .map(String::from)
+            .take_while(|id| id != reached)
+            .chain(Some(reached.to_string()))
+            .filter(|id| id != inserted)
+            .collect();
+        let progress: String = ids
+            .iter()
+            .map(|id| format!("include!(\"koans/{}.rs\");\n", id))
+            .collect();
+        write(progress_path, progress).unwrap();
+
+        let koans = KoanCollection::new("src/koans", progress_path);
+        assert_eq!(koans.n_opened(), ids.len());
+        assert_eq!(
+            koans.opened().last().map(String::from),
+            Some(reached.to_string())
+        );
+        // The new koan comes next, rather than a koan the learner has already opened
+        assert_eq!(koans.next().map(String::from), Some(inserted.to_string()));
+        remove_file(progress_path).unwrap();
+    }
+}
@@ -4,26 +4,79 @@
 
 use ansi_term::Colour::{Green, Red, White, Yellow};
 use ansi_term::Style;
+use koans::aggregate::{Aggregate, Progress};
+use koans::doctor::{diagnose, Severity};
+use koans::i18n::{fill, Locale, Message};
+use koans::report::{Report, ReportFormat};
+use koans::session::format_duration;
+use koans::session::Session;
 use koans::KoanCollection;
+use std::fs::{read_dir, read_to_string, write};
+use std::path::Path;
 use std::process::{Command, ExitStatus, Stdio};
 
+const PROGRESS_PATH: &str = "src/path_to_enlightenment.rs";
+const SESSION_PATH: &str = ".koans_session.json";
+const TRANSLATIONS_PATH: &str = "translations";
+
 fn main() {
-    let mut koans = KoanCollection::new("src/koans", "src/path_to_enlightenment.rs");
-    let message = if !seek_the_path(&koans) || walk_the_path(&mut koans) {
-        "Eternity lies ahead of us, and behind. Your path is not yet finished. 🍂"
-    } else {
-        "What is the sound of one hand clapping (for you)? 🌟"
-    };
+    let mut koans = KoanCollection::new("src/koans", PROGRESS_PATH);
+    let mut session = Session::load(SESSION_PATH);
+
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let lang_flag = take_flag_value(&mut args, "--lang");
+    let locale = Locale::detect(lang_flag.as_deref());
 
-    println!("\t{}\n", Style::default().italic().paint(message));
+    match args.first().map(String::as_str) {
+        None => {
+            koans.initialise();
+            let message = if !seek_the_path(&koans, &mut session, locale)
+                || walk_the_path(&mut koans, &mut session, locale)
+            {
+                locale.message(Message::PathNotFinished)
+            } else {
+                session.record_completion();
+                println!("\n{}", Report::new(&session, locale).to_terminal());
+                locale.message(Message::PathFinished)
+            };
+            session.save(SESSION_PATH);
+
+            println!("\t{}\n", Style::default().italic().paint(message));
+        }
+        Some("report") => report(&session, &args[1..], locale),
+        Some("aggregate") => match &args[1..] {
+            [dir] => aggregate(Path::new(dir), locale),
+            _ => usage(locale),
+        },
+        Some("doctor") => doctor(locale),
+        Some("hint") => {
+            hint(&koans, &mut session, locale);
+            session.save(SESSION_PATH);
+        }
+        Some(_) => usage(locale),
+    }
+}
+
+/// Remove `flag` and the value following it from `args`, returning the value.
+fn take_flag_value(args: &mut Vec<String>, flag: &str) -> Option<String> {
+    let position = args.iter().position(|a| a == flag)?;
+    if position + 1 < args.len() {
+        args.remove(position);
+        Some(args.remove(position))
+    } else {
+        args.remove(position);
+        None
+    }
 }
 
-fn seek_the_path(koans: &KoanCollection) -> bool {
+fn seek_the_path(koans: &KoanCollection, session: &mut Session, locale: Locale) -> bool {
     print!(" \n\n");
     for koan in koans.opened() {
+        let koan_id: String = koan.into();
         let koan_outcome = run_tests(Some(&koan.name));
         match koan_outcome {
             TestOutcome::Success => {
+                session.record_success(&koan_id);
                 println!(
                     "\t🚀 {} - {}️",
                     Green.normal().paint(&koan.parent_name),
@@ -31,12 +84,13 @@ fn seek_the_path(koans: &KoanCollection) -> bool {
                 );
             }
             TestOutcome::Failure { details } => {
+                session.record_failure(&koan_id);
                 println!(
                     "\t❌ {}\n\n\t{}\n\n{}",
                     Red.normal().paint(&koan.name),
-                    Style::default().italic().paint(
-                        "Meditate on your approach and return. Mountains are merely mountains."
-                    ),
+                    Style::default()
+                        .italic()
+                        .paint(locale.message(Message::Meditate)),
                     Style::default().dimmed().paint(details)
                 );
                 return false;
@@ -46,26 +100,204 @@ fn seek_the_path(koans: &KoanCollection) -> bool {
     true
 }
 
-fn walk_the_path(koans: &mut KoanCollection) -> bool {
-    if let Ok(new_koan) = koans.open_next() {
+fn walk_the_path(koans: &mut KoanCollection, session: &mut Session, locale: Locale) -> bool {
+    if let Some(new_koan) = koans.open_next() {
+        let koan_id = String::from(new_koan);
+        session.record_opened(&koan_id);
         println!(
             "{} {} - {}.",
-            Yellow.normal().paint("\n\tAhead of you lies"),
+            Yellow
+                .normal()
+                .paint(format!("\n\t{}", locale.message(Message::AheadOfYouLies))),
             Yellow.bold().paint(&new_koan.parent_name),
             Yellow.bold().paint(&new_koan.name)
         );
+        print_koan_prose(&koan_id, locale);
         true
     } else {
         println!(
             "{}",
-            Green.normal().paint("\n\tThere will be no more tasks.")
+            Green
+                .normal()
+                .paint(format!("\n\t{}", locale.message(Message::NoMoreTasks)))
         );
         false
     }
 }
 
+/// Print the translated prose of a koan, if there is a sidecar file for the current locale.
+fn print_koan_prose(koan_id: &str, locale: Locale) {
+    if let Some(prose) = locale.koan_prose(TRANSLATIONS_PATH, koan_id) {
+        println!();
+        for line in prose.lines() {
+            println!("\t{}", Style::default().dimmed().paint(line));
+        }
+    }
+}
+
+/// `koans report [--export <path>]`: print the session report, or export it
+/// to Markdown, HTML or JSON depending on the extension of `path`.
+fn report(session: &Session, args: &[String], locale: Locale) {
+    match args {
+        [] => println!("\n{}", Report::new(session, locale).to_terminal()),
+        [flag, path] if flag == "--export" => {
+            let format = ReportFormat::from_path(path).unwrap_or_else(|| {
+                panic!(
+                    "Unsupported report format for {}: use a `.md`, `.html` or `.json` extension.",
+                    path
+                )
+            });
+            write(path, Report::new(session, locale).render(format))
+                .expect("Failed to write the report");
+            println!(
+                "\n\t{} {}.\n",
+                locale.message(Message::ReportExportedTo),
+                Yellow.bold().paint(path)
+            );
+        }
+        _ => usage(locale),
+    }
+}
+
+/// `koans aggregate <dir>`: merge the progress files exported by a whole workshop room
+/// to spot the koans everybody is stuck on.
+fn aggregate(dir: &Path, locale: Locale) {
+    let mut progresses = Vec::new();
+    for entry in read_dir(dir).expect("Failed to read the progress directory") {
+        let path = entry.expect("Failed to read the progress directory").path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        match Progress::load(&path) {
+            Ok(progress) => progresses.push(progress),
+            Err(e) => eprintln!(
+                "\t{} {}",
+                Red.normal().paint(locale.message(Message::Skipping)),
+                e
+            ),
+        }
+    }
+
+    let aggregate = Aggregate::new(&progresses);
+    println!(
+        "\n\t{}\n",
+        fill(
+            locale.message(Message::LearnersSummary),
+            &[&aggregate.n_learners, &aggregate.n_completed]
+        )
+    );
+    println!(
+        "\t{:<45} {:<20} {:>6} {:>14}",
+        locale.message(Message::Koan),
+        locale.message(Message::Stuck),
+        locale.message(Message::Solved),
+        locale.message(Message::AverageTime)
+    );
+    for koan in &aggregate.koans {
+        println!(
+            "\t{:<45} {:<20} {:>6} {:>14}",
+            koan.id,
+            format!("{} {}", "█".repeat(koan.n_stuck), koan.n_stuck),
+            koan.n_solved,
+            koan.average_time_to_solve
+                .map(format_duration)
+                .unwrap_or_else(|| "-".into())
+        );
+    }
+    if let Some(koan) = aggregate.bottleneck() {
+        println!(
+            "\n\t{} {} ({} {})\n",
+            Yellow
+                .normal()
+                .paint(locale.message(Message::MostLearnersStuckOn)),
+            Yellow.bold().paint(&koan.id),
+            koan.n_stuck,
+            locale.message(Message::Learners)
+        );
+    }
+}
+
+/// `koans doctor`: a preflight check of the learner's environment.
+fn doctor(locale: Locale) {
+    println!();
+    let diagnoses = diagnose(PROGRESS_PATH, locale);
+    for diagnosis in &diagnoses {
+        let icon = match diagnosis.severity {
+            Severity::Ok => Green.normal().paint("✔"),
+            Severity::Warning => Yellow.normal().paint("⚠"),
+            Severity::Problem => Red.normal().paint("✘"),
+        };
+        println!("\t{} {}", icon, diagnosis.summary);
+        if let Some(fix) = &diagnosis.fix {
+            println!("\t  {}", Style::default().dimmed().paint(fix));
+        }
+    }
+
+    let n_problems = diagnoses
+        .iter()
+        .filter(|d| d.severity == Severity::Problem)
+        .count();
+    if n_problems == 0 {
+        println!(
+            "\n\t{}\n",
+            Green.normal().paint(locale.message(Message::DoctorAllGood))
+        );
+    } else {
+        println!(
+            "\n\t{}\n",
+            Red.normal().paint(fill(
+                locale.message(Message::DoctorProblemsFound),
+                &[&n_problems]
+            ))
+        );
+        std::process::exit(1);
+    }
+}
+
+/// `koans hint`: point at the placeholders left to fill in the koan you are working on.
+fn hint(koans: &KoanCollection, session: &mut Session, locale: Locale) {
+    let koan = match koans.opened().last() {
+        Some(koan) => koan,
+        None => {
+            println!("\n\t{}\n", locale.message(Message::OpenYourFirstKoan));
+            return;
+        }
+    };
+    let koan_id = String::from(koan);
+    session.record_hint(&koan_id);
+    print_koan_prose(&koan_id, locale);
+
+    let source_path = koans.source_path(koan);
+    let source = read_to_string(&source_path).expect("Failed to read the koan source");
+    println!(
+        "\n\t{} {}\n",
+        Yellow
+            .normal()
+            .paint(locale.message(Message::LookForTheBlanks)),
+        Yellow.bold().paint(source_path.display().to_string())
+    );
+    for (line_number, line) in source.lines().enumerate() {
+        if line.contains("__") {
+            println!(
+                "\t{:>4} {}",
+                Style::default()
+                    .dimmed()
+                    .paint((line_number + 1).to_string()),
+                line.trim()
+            );
+        }
+    }
+    println!();
+}
+
+fn usage(locale: Locale) {
+    println!("\n\t{}\n", locale.message(Message::Usage));
+}
+
 fn run_tests(filter: Option<&str>) -> TestOutcome {
-    let mut args = vec!["test", "-q"];
+    // Koans live in the runner binary: the library's own tests and doctests
+    // should not slow learners down (or fail on them) when a filter matches their names.
+    let mut args = vec!["test", "-q", "--bin", "koans"];
 
     if let Some(test_filter) = filter {
         args.push(test_filter);
@@ -94,5 +326,8 @@ enum TestOutcome {
     Failure { details: String },
 }
 
-#[cfg(test)]
+// The progress file is created by the runner (`cargo run`), not by the build.
+// `build.rs` tells us whether it exists: until it does, there are no koans to test,
+// while the library's own tests can run on a fresh checkout.
+#[cfg(all(test, not(missing_path_to_enlightenment)))]
 mod path_to_enlightenment;
@@ -0,0 +1,369 @@
+use crate::aggregate::Progress;
+use crate::i18n::{Locale, Message};
+use crate::session::{format_duration, KoanRecord, Session};
+use ansi_term::Colour::{Green, Red, Yellow};
+use ansi_term::Style;
+use std::fmt::Write;
+use std::path::Path;
+
+/// How many koans are listed in the "hardest koans" section of a report.
+const N_HARDEST: usize = 3;
+
+/// The formats a report can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+    /// The raw session data, to be merged with other learners' by `koans aggregate`.
+    Json,
+}
+
+impl ReportFormat {
+    /// Infer the format from the extension of `path` (`.md`, `.html` or `.json`).
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        match path.as_ref().extension()?.to_str()? {
+            "md" | "markdown" => Some(ReportFormat::Markdown),
+            "html" | "htm" => Some(ReportFormat::Html),
+            "json" => Some(ReportFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// A summary of a learner's session: total time, attempts per koan,
+/// hardest koans by failures and hints used.
+pub struct Report<'a> {
+    session: &'a Session,
+    locale: Locale,
+}
+
+impl<'a> Report<'a> {
+    pub fn new(session: &'a Session, locale: Locale) -> Self {
+        Self { session, locale }
+    }
+
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Markdown => self.to_markdown(),
+            ReportFormat::Html => self.to_html(),
+            ReportFormat::Json => serde_json::to_string_pretty(&Progress::new(self.session))
+                .expect("Failed to serialize the session"),
+        }
+    }
+
+    /// A colourful rendition of the report, meant to be printed in the terminal.
+    pub fn to_terminal(&self) -> String {
+        let session = self.session;
+        let mut out = String::new();
+
+        writeln!(out, "\t{}\n", Style::default().bold().paint(self.title())).unwrap();
+        writeln!(
+            out,
+            "\t{}: {}",
+            self.t(Message::TotalTime),
+            Yellow.normal().paint(format_duration(session.total_time()))
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "\t{}: {}",
+            self.t(Message::Attempts),
+            session.total_attempts()
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "\t{}: {}\n",
+            self.t(Message::HintsUsed),
+            session.total_hints()
+        )
+        .unwrap();
+
+        for koan in &session.koans {
+            let status = if koan.solved_at.is_some() {
+                Green.normal().paint("✔")
+            } else {
+                Red.normal().paint("✘")
+            };
+            writeln!(
+                out,
+                "\t{} {:<45} {:>3} {} {:>3} {} {:>3} {} {:>12}",
+                status,
+                koan.id,
+                koan.attempts,
+                self.t(Message::Attempts).to_lowercase(),
+                koan.failures,
+                self.t(Message::Failures).to_lowercase(),
+                koan.hints,
+                self.t(Message::Hints).to_lowercase(),
+                time_to_solve(koan)
+            )
+            .unwrap();
+        }
+
+        let hardest = session.hardest(N_HARDEST);
+        if !hardest.is_empty() {
+            writeln!(
+                out,
+                "\n\t{}",
+                Style::default().bold().paint(self.t(Message::HardestKoans))
+            )
+            .unwrap();
+            for (rank, koan) in hardest.iter().enumerate() {
+                writeln!(
+                    out,
+                    "\t{}. {} ({} {})",
+                    rank + 1,
+                    Red.normal().paint(&koan.id),
+                    koan.failures,
+                    self.t(Message::Failures).to_lowercase()
+                )
+                .unwrap();
+            }
+        }
+        out
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let session = self.session;
+        let mut out = String::new();
+
+        writeln!(out, "# {}\n", self.title()).unwrap();
+        writeln!(
+            out,
+            "- **{}**: {}",
+            self.t(Message::TotalTime),
+            format_duration(session.total_time())
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "- **{}**: {}",
+            self.t(Message::Attempts),
+            session.total_attempts()
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "- **{}**: {}\n",
+            self.t(Message::HintsUsed),
+            session.total_hints()
+        )
+        .unwrap();
+
+        writeln!(out, "## {}\n", self.t(Message::Koans)).unwrap();
+        writeln!(
+            out,
+            "| {} | {} | {} | {} | {} | {} |",
+            self.t(Message::Koan),
+            self.t(Message::Solved),
+            self.t(Message::Attempts),
+            self.t(Message::Failures),
+            self.t(Message::Hints),
+            self.t(Message::TimeToSolve)
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "|------|:------:|---------:|---------:|------:|--------------:|"
+        )
+        .unwrap();
+        for koan in &session.koans {
+            writeln!(
+                out,
+                "| `{}` | {} | {} | {} | {} | {} |",
+                koan.id,
+                if koan.solved_at.is_some() {
+                    "✔"
+                } else {
+                    "✘"
+                },
+                koan.attempts,
+                koan.failures,
+                koan.hints,
+                time_to_solve(koan)
+            )
+            .unwrap();
+        }
+
+        let hardest = session.hardest(N_HARDEST);
+        if !hardest.is_empty() {
+            writeln!(out, "\n## {}\n", self.t(Message::HardestKoans)).unwrap();
+            for (rank, koan) in hardest.iter().enumerate() {
+                writeln!(
+                    out,
+                    "{}. `{}` ({} {})",
+                    rank + 1,
+                    koan.id,
+                    koan.failures,
+                    self.t(Message::Failures).to_lowercase()
+                )
+                .unwrap();
+            }
+        }
+        out
+    }
+
+    pub fn to_html(&self) -> String {
+        let session = self.session;
+        let mut out = String::new();
+
+        writeln!(out, "<!DOCTYPE html>").unwrap();
+        writeln!(out, "<html lang=\"{}\">", self.locale.code()).unwrap();
+        writeln!(out, "<head>\n<meta charset=\"utf-8\">").unwrap();
+        writeln!(out, "<title>{}</title>", escape_html(self.title())).unwrap();
+        writeln!(
+            out,
+            "<style>body {{ font-family: sans-serif; max-width: 50em; margin: auto; }} \
+             table {{ border-collapse: collapse; }} \
+             td, th {{ border: 1px solid #ccc; padding: 0.3em 0.8em; }}</style>"
+        )
+        .unwrap();
+        writeln!(out, "</head>\n<body>").unwrap();
+        writeln!(out, "<h1>{}</h1>", escape_html(self.title())).unwrap();
+        writeln!(out, "<ul>").unwrap();
+        writeln!(
+            out,
+            "<li><strong>{}</strong>: {}</li>",
+            self.t(Message::TotalTime),
+            format_duration(session.total_time())
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "<li><strong>{}</strong>: {}</li>",
+            self.t(Message::Attempts),
+            session.total_attempts()
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "<li><strong>{}</strong>: {}</li>",
+            self.t(Message::HintsUsed),
+            session.total_hints()
+        )
+        .unwrap();
+        writeln!(out, "</ul>").unwrap();
+
+        writeln!(out, "<h2>{}</h2>\n<table>", self.t(Message::Koans)).unwrap();
+        writeln!(
+            out,
+            "<tr><th>{}</th><th>{}</th><th>{}</th><th>{}</th><th>{}</th><th>{}</th></tr>",
+            self.t(Message::Koan),
+            self.t(Message::Solved),
+            self.t(Message::Attempts),
+            self.t(Message::Failures),
+            self.t(Message::Hints),
+            self.t(Message::TimeToSolve)
+        )
+        .unwrap();
+        for koan in &session.koans {
+            writeln!(
+                out,
+                "<tr><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape_html(&koan.id),
+                if koan.solved_at.is_some() { "✔" } else { "✘" },
+                koan.attempts,
+                koan.failures,
+                koan.hints,
+                time_to_solve(koan)
+            )
+            .unwrap();
+        }
+        writeln!(out, "</table>").unwrap();
+
+        let hardest = session.hardest(N_HARDEST);
+        if !hardest.is_empty() {
+            writeln!(
+                out,
+                "<h2>{}</h2>\n<ol>",
+                escape_html(self.t(Message::HardestKoans))
+            )
+            .unwrap();
+            for koan in hardest {
+                writeln!(
+                    out,
+                    "<li><code>{}</code> ({} {})</li>",
+                    escape_html(&koan.id),
+                    koan.failures,
+                    self.t(Message::Failures).to_lowercase()
+                )
+                .unwrap();
+            }
+            writeln!(out, "</ol>").unwrap();
+        }
+        writeln!(out, "</body>\n</html>").unwrap();
+        out
+    }
+
+    fn title(&self) -> &'static str {
+        if self.session.is_completed() {
+            self.t(Message::ReportTitleCompleted)
+        } else {
+            self.t(Message::ReportTitleInProgress)
+        }
+    }
+
+    fn t(&self, message: Message) -> &'static str {
+        self.locale.message(message)
+    }
+}
+
+fn time_to_solve(koan: &KoanRecord) -> String {
+    koan.time_to_solve()
+        .map(format_duration)
+        .unwrap_or_else(|| "-".into())
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn koan_ids_are_escaped_in_html() {
+        let mut session = Session::new();
+        session.record_failure("<b>\"koans\" & co</b>");
+        let html = Report::new(&session, Locale::English).to_html();
+
+        assert!(html.contains("<code>&lt;b&gt;&quot;koans&quot; &amp; co&lt;/b&gt;</code>"));
+        assert!(!html.contains("<b>"));
+    }
+
+    #[test]
+    fn koan_ids_are_code_spans_in_markdown() {
+        let mut session = Session::new();
+        session.record_failure("04_initialisation/02_k_means_plus_plus");
+        let markdown = Report::new(&session, Locale::English).to_markdown();
+
+        // Outside of a code span the underscores would turn into emphasis
+        assert!(
+            markdown.contains("| `04_initialisation/02_k_means_plus_plus` | ✘ | 1 | 1 | 0 | - |")
+        );
+        assert!(markdown.contains("1. `04_initialisation/02_k_means_plus_plus` (1 failures)"));
+    }
+
+    #[test]
+    fn zero_koan_sessions_render_an_empty_table() {
+        let session = Session::new();
+        let report = Report::new(&session, Locale::English);
+
+        let markdown = report.to_markdown();
+        assert!(
+            markdown.ends_with("|------|:------:|---------:|---------:|------:|--------------:|\n")
+        );
+        assert!(!markdown.contains("Hardest koans"));
+
+        let html = report.to_html();
+        assert!(html.contains("</tr>\n</table>"));
+        assert!(!html.contains("<ol>"));
+
+        assert!(!report.to_terminal().contains("Hardest koans"));
+    }
+}
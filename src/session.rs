@@ -0,0 +1,272 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{rename, File};
+use std::io::{self, BufReader, BufWriter, ErrorKind, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Everything we remember about a learner's journey along the path to enlightenment.
+///
+/// It is persisted as JSON between runs of the runner, given that each `cargo run`
+/// only lasts long enough to check the koans opened so far.
+/// Timestamps are seconds since the Unix epoch.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Session {
+    pub started_at: u64,
+    pub last_activity_at: u64,
+    pub completed_at: Option<u64>,
+    pub koans: Vec<KoanRecord>,
+}
+
+/// What happened to a single koan, identified by its `dd_chapter/dd_name` path.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KoanRecord {
+    pub id: String,
+    /// Number of runs that checked this koan while it was still unsolved
+    /// (the run that solved it included).
+    pub attempts: u32,
+    pub failures: u32,
+    pub hints: u32,
+    pub opened_at: u64,
+    pub solved_at: Option<u64>,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        let now = now();
+        Self {
+            started_at: now,
+            last_activity_at: now,
+            completed_at: None,
+            koans: Vec::new(),
+        }
+    }
+
+    /// Load the session stored at `path`, starting a new one if there is none yet.
+    ///
+    /// A file that cannot be parsed (e.g. left half-written by an interrupted run)
+    /// is reported and replaced by a new session, rather than blocking every later run.
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Self::new(),
+            Err(e) => panic!("Failed to read the session file {}: {}", path.display(), e),
+        };
+        serde_json::from_reader(BufReader::new(file)).unwrap_or_else(|e| {
+            eprintln!(
+                "Warning: the session file {} is corrupt ({}), starting a new session.",
+                path.display(),
+                e
+            );
+            Self::new()
+        })
+    }
+
+    /// Save the session to `path`.
+    ///
+    /// The session is written to a temporary file first and then moved into place,
+    /// so that an interrupted run never leaves a truncated file behind.
+    pub fn save(&self, path: impl AsRef<Path>) {
+        let path = path.as_ref();
+        let mut temporary_path = path.as_os_str().to_owned();
+        temporary_path.push(".tmp");
+        let write = || -> io::Result<()> {
+            let mut writer = BufWriter::new(File::create(&temporary_path)?);
+            serde_json::to_writer_pretty(&mut writer, self)?;
+            writer.flush()?;
+            rename(&temporary_path, path)
+        };
+        write().unwrap_or_else(|e| {
+            panic!("Failed to write the session file {}: {}", path.display(), e)
+        });
+    }
+
+    pub fn record_opened(&mut self, koan_id: &str) {
+        self.record(koan_id);
+    }
+
+    pub fn record_success(&mut self, koan_id: &str) {
+        let now = now();
+        let record = self.record(koan_id);
+        if record.solved_at.is_none() {
+            record.attempts += 1;
+            record.solved_at = Some(now);
+        }
+    }
+
+    pub fn record_failure(&mut self, koan_id: &str) {
+        let record = self.record(koan_id);
+        record.attempts += 1;
+        record.failures += 1;
+    }
+
+    pub fn record_hint(&mut self, koan_id: &str) {
+        self.record(koan_id).hints += 1;
+    }
+
+    pub fn record_completion(&mut self) {
+        if self.completed_at.is_none() {
+            self.completed_at = Some(now());
+        }
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.completed_at.is_some()
+    }
+
+    /// Seconds elapsed between the first run and the completion of the path
+    /// (or the latest run, if the path is not finished yet).
+    pub fn total_time(&self) -> u64 {
+        self.completed_at
+            .unwrap_or(self.last_activity_at)
+            .saturating_sub(self.started_at)
+    }
+
+    pub fn total_attempts(&self) -> u32 {
+        self.koans.iter().map(|k| k.attempts).sum()
+    }
+
+    pub fn total_hints(&self) -> u32 {
+        self.koans.iter().map(|k| k.hints).sum()
+    }
+
+    /// The first koan that has been opened but not solved yet, the one the learner is stuck on.
+    pub fn current_koan(&self) -> Option<&KoanRecord> {
+        self.koans.iter().find(|k| k.solved_at.is_none())
+    }
+
+    /// The `n` koans with the highest number of failures, hardest first.
+    /// Koans that never failed are left out.
+    pub fn hardest(&self, n: usize) -> Vec<&KoanRecord> {
+        let mut koans: Vec<&KoanRecord> = self.koans.iter().filter(|k| k.failures > 0).collect();
+        // The sort is stable: ties are broken by the order in which koans were opened
+        koans.sort_by_key(|k| std::cmp::Reverse(k.failures));
+        koans.truncate(n);
+        koans
+    }
+
+    // Get the record for `koan_id`, creating it if this is the first time we hear about it.
+    fn record(&mut self, koan_id: &str) -> &mut KoanRecord {
+        let now = now();
+        self.last_activity_at = now;
+        let position = match self.koans.iter().position(|k| k.id == koan_id) {
+            Some(position) => position,
+            None => {
+                self.koans.push(KoanRecord {
+                    id: koan_id.to_string(),
+                    attempts: 0,
+                    failures: 0,
+                    hints: 0,
+                    opened_at: now,
+                    solved_at: None,
+                });
+                self.koans.len() - 1
+            }
+        };
+        &mut self.koans[position]
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KoanRecord {
+    /// Seconds spent between opening the koan and solving it, if it has been solved.
+    pub fn time_to_solve(&self) -> Option<u64> {
+        self.solved_at.map(|s| s.saturating_sub(self.opened_at))
+    }
+}
+
+/// Format a number of seconds as `1h 02m 03s`.
+pub fn format_duration(seconds: u64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, (seconds % 3600) / 60, seconds % 60);
+    if hours > 0 {
+        format!("{}h {:02}m {:02}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {:02}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("The system clock is set before the Unix epoch")
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+    use std::fs::{remove_file, write};
+
+    #[test]
+    fn attempts_stop_counting_once_solved() {
+        let mut session = Session::new();
+        session.record_opened("00_greetings/00_greetings");
+        session.record_failure("00_greetings/00_greetings");
+        session.record_failure("00_greetings/00_greetings");
+        session.record_success("00_greetings/00_greetings");
+        // Later runs check the koan again, but it was already solved
+        session.record_success("00_greetings/00_greetings");
+
+        let record = &session.koans[0];
+        assert_eq!(record.attempts, 3);
+        assert_eq!(record.failures, 2);
+        assert!(record.solved_at.is_some());
+    }
+
+    #[test]
+    fn hardest_koans_are_sorted_by_failures() {
+        let mut session = Session::new();
+        session.record_failure("a");
+        session.record_failure("b");
+        session.record_failure("b");
+        session.record_success("c");
+        session.record_failure("d");
+
+        let hardest: Vec<&str> = session.hardest(2).iter().map(|k| k.id.as_str()).collect();
+        assert_eq!(hardest, vec!["b", "a"]);
+    }
+
+    #[test]
+    fn durations_are_human_readable() {
+        assert_eq!(format_duration(42), "42s");
+        assert_eq!(format_duration(62), "1m 02s");
+        assert_eq!(format_duration(3723), "1h 02m 03s");
+    }
+
+    #[test]
+    fn sessions_round_trip_through_the_session_file() {
+        let path = temp_dir().join("koans_session_round_trip.json");
+        let mut session = Session::new();
+        session.record_failure("00_greetings/00_greetings");
+        session.save(&path);
+
+        let loaded = Session::load(&path);
+        remove_file(&path).unwrap();
+        assert_eq!(loaded.koans.len(), 1);
+        assert_eq!(loaded.koans[0].failures, 1);
+    }
+
+    #[test]
+    fn corrupt_session_files_start_a_new_session() {
+        let path = temp_dir().join("koans_session_corrupt.json");
+        write(&path, "{\"started_at\": 12").unwrap();
+
+        let session = Session::load(&path);
+        remove_file(&path).unwrap();
+        assert!(session.koans.is_empty());
+    }
+
+    #[test]
+    fn missing_session_files_start_a_new_session() {
+        let session = Session::load(temp_dir().join("koans_session_missing.json"));
+        assert!(session.koans.is_empty());
+    }
+}